@@ -0,0 +1,181 @@
+//! Simple aerodynamics model
+
+use crate::utils::{
+    file_utils::{self, Validate},
+    math_algorithms,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::MassProperties;
+use serde::{Deserialize, Serialize};
+
+/// Global air properties. Must be added to the app; game loads it from
+/// [`Atmosphere::FILENAME`].
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Atmosphere {
+    /// Kilograms per cubic meter
+    pub air_density: f32,
+    /// Velocity of air, meters per second
+    pub wind: Vec3,
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Self {
+            air_density: 1.225,
+            wind: Vec3::ZERO,
+        }
+    }
+}
+
+impl Validate for Atmosphere {
+    fn validate(&self) -> Result<(), String> {
+        file_utils::check_finite([("air_density", self.air_density)])?;
+        if self.air_density < 0. {
+            return Err("air_density can't be negative".to_string());
+        }
+        if !self.wind.is_finite() {
+            return Err("wind must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Atmosphere {
+    pub const FILENAME: &'static str = "config/atmosphere.ron";
+}
+
+/// Aerodynamic properties of a body.
+///
+/// All per-axis values are in local space of the body: X is side, Y is top, Z
+/// is front.
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Aerodynamics {
+    /// Cross-section area, square meters
+    pub area: Vec3,
+    /// Drag coefficient, depends on object shape
+    pub drag_coeff: Vec3,
+    /// Lift coefficient of the top area for forward movement; negative values
+    /// create downforce
+    pub lift_coeff: f32,
+    /// Angular damping around each axis, per second
+    pub angular_damping: Vec3,
+}
+
+impl Default for Aerodynamics {
+    fn default() -> Self {
+        Self {
+            area: Vec3::new(12., 48., 16.),
+            drag_coeff: Vec3::new(0.6, 1.1, 0.09),
+            lift_coeff: 0.,
+            angular_damping: Vec3::splat(0.5),
+        }
+    }
+}
+
+impl Validate for Aerodynamics {
+    fn validate(&self) -> Result<(), String> {
+        let vectors = [self.area, self.drag_coeff, self.angular_damping];
+        if !vectors.iter().all(|vector| vector.is_finite()) || !self.lift_coeff.is_finite() {
+            return Err("aerodynamic properties must be finite".to_string());
+        }
+        if self.area.min_element() < 0. {
+            return Err("aerodynamic area can't be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Aerodynamics {
+    /// Drag force in world space.
+    ///
+    /// `air_velocity` is velocity of the body relative to the air.
+    pub fn drag(&self, rotation: Quat, air_velocity: Vec3, air_density: f32) -> Vec3 {
+        let local = rotation.inverse() * air_velocity;
+        let force = -0.5 * air_density * self.drag_coeff * self.area * local * local.abs();
+        rotation * force
+    }
+
+    /// Lift (or downforce) in world space, along the up vector of the body.
+    /// Only forward movement creates it.
+    ///
+    /// `air_velocity` is velocity of the body relative to the air.
+    pub fn lift(&self, rotation: Quat, air_velocity: Vec3, air_density: f32) -> Vec3 {
+        // forward is -Z
+        let forward_speed = (-(rotation.inverse() * air_velocity).z).max(0.);
+        let force = 0.5 * air_density * self.lift_coeff * self.area.y * forward_speed.powi(2);
+        rotation * Vec3::Y * force
+    }
+
+    /// Damping torque in world space
+    pub fn angular_damping_torque(
+        &self,
+        rotation: Quat,
+        angular_velocity: Vec3,
+        mass_properties: &MassProperties,
+    ) -> Vec3 {
+        let principal_frame = rotation * mass_properties.principal_inertia_local_frame;
+        let local = rotation.inverse() * angular_velocity;
+
+        [Vec3::X, Vec3::Y, Vec3::Z]
+            .into_iter()
+            .map(|axis| {
+                let world_axis = rotation * axis;
+                let inertia = math_algorithms::inertia_around_axis(
+                    mass_properties.principal_inertia,
+                    principal_frame,
+                    world_axis,
+                );
+                -world_axis * local.dot(axis) * self.angular_damping.dot(axis) * inertia
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use approx::*;
+
+    const ERROR: f32 = 0.0001;
+
+    #[test]
+    fn drag_per_axis() {
+        let aero = Aerodynamics {
+            area: Vec3::new(1., 2., 3.),
+            drag_coeff: Vec3::ONE,
+            ..default()
+        };
+
+        // 0.5 * density * coeff * area * speed^2, against movement
+        let drag = aero.drag(Quat::IDENTITY, Vec3::new(0., 0., -2.), 1.);
+        assert_relative_eq!(drag, Vec3::new(0., 0., 6.), epsilon = ERROR);
+
+        let drag = aero.drag(Quat::IDENTITY, Vec3::new(2., 0., 0.), 1.);
+        assert_relative_eq!(drag, Vec3::new(-2., 0., 0.), epsilon = ERROR);
+
+        // same movement, but body is turned sideways
+        let rotation = Quat::from_rotation_y(std::f32::consts::PI / 2.);
+        let drag = aero.drag(rotation, Vec3::new(0., 0., -2.), 1.);
+        assert_relative_eq!(drag, Vec3::new(0., 0., 2.), epsilon = ERROR);
+    }
+
+    #[test]
+    fn lift() {
+        let aero = Aerodynamics {
+            area: Vec3::new(0., 2., 0.),
+            lift_coeff: -1.,
+            ..default()
+        };
+
+        let lift = aero.lift(Quat::IDENTITY, Vec3::new(0., 0., -3.), 1.);
+        assert_relative_eq!(lift, Vec3::new(0., -9., 0.), epsilon = ERROR);
+
+        // moving backwards or sideways
+        let lift = aero.lift(Quat::IDENTITY, Vec3::new(0., 0., 3.), 1.);
+        assert_relative_eq!(lift, Vec3::ZERO, epsilon = ERROR);
+        let lift = aero.lift(Quat::IDENTITY, Vec3::new(3., 0., 0.), 1.);
+        assert_relative_eq!(lift, Vec3::ZERO, epsilon = ERROR);
+    }
+}
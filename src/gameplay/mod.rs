@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 
+pub mod aerodynamics;
 pub mod objects;
+pub mod physics;
 pub mod spawn;
 
+/// Requires [`aerodynamics::Atmosphere`] resource
 pub struct GameplayPlugin;
 
 impl Plugin for GameplayPlugin {
@@ -1,46 +1,476 @@
-use crate::utils::{for_crate::bevy::FallibleCommands, math_algorithms};
+use crate::{
+    gameplay::aerodynamics::{Aerodynamics, Atmosphere},
+    utils::{
+        file_utils::{self, Validate},
+        for_crate::bevy::FallibleCommands,
+        math_algorithms,
+        plugins::{
+            console::{parse_console_args, set_reflect_field, ConsoleApp},
+            crash_report::{remove_crash_settings, set_crash_settings},
+            file_watcher::{FileChanged, WatchedFiles},
+        },
+    },
+};
 use bevy::{math::Vec3Swizzles, prelude::*};
 use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+/// Marker of the hovercrab, other components are added automatically.
+///
+/// Controlled by [`HovercrabControls`].
 #[derive(Component, Default)]
-pub struct Hovercrab {
-    camera_entity: Option<Entity>,
+pub struct Hovercrab;
 
-    input_dir: Vec3, // normalized by XZ, but not by Y
-    input_accel: bool,
-    input_stop: bool,
-    target_rotation: Vec3,
+/// What hovercrab should do. Gameplay only reads this; it's written by
+/// controllers (local player, AI, network, replay, etc) in
+/// [`HovercrabControlsSet`].
+///
+/// Added automatically.
+#[derive(Component, Clone, Default)]
+pub struct HovercrabControls {
+    /// Direction of movement relative to current heading of the hovercrab: -Z
+    /// is forward, X is right. XZ length is at most 1; Y is up or down and
+    /// isn't limited by it.
+    pub movement: Vec3,
+    /// How much boost is applied, from zero to one
+    pub boost: f32,
+    pub brake: bool,
+    /// Hop is charged while this is true, and performed when it's not
+    pub hop: bool,
+    /// Direction in which hovercrab should face; only XZ is used. If zero,
+    /// hovercrab doesn't turn.
+    pub target_rotation: Vec3,
 }
 
-impl Hovercrab {
-    pub fn new(camera_entity: Entity) -> Self {
+/// Systems which write [`HovercrabControls`] must be in this set in
+/// [`FixedUpdate`] schedule.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct HovercrabControlsSet;
+
+/// Current state of the hovercrab, can be shown in HUD.
+///
+/// Added automatically.
+#[derive(Component, Default)]
+pub struct HovercrabStatus {
+    /// Energy used for boost, from zero to [`HovercrabParams::boost_energy`]
+    pub boost_energy: f32,
+    /// Boost is currently applied
+    pub boosting: bool,
+    /// Air-brake is currently applied
+    pub braking: bool,
+
+    /// Current target distance from the ground to the bottom of the body
+    pub hover_height: f32,
+    /// Hop is charging, from zero to one
+    pub hop_charge: f32,
+    /// Seconds until hop can be charged again
+    pub hop_cooldown: f32,
+
+    /// Averaged normal of the ground under the hover rays; if there is no
+    /// ground, it's up vector.
+    pub ground_normal: Vec3,
+
+    /// State of each hover thruster, in order of [`ThrusterLayout::positions`]
+    pub rays: Vec<HovercrabRayStatus>,
+    /// Total torque applied during the last physics step, Newton-meters
+    pub torque: Vec3,
+}
+
+/// State of single hover thruster
+#[derive(Clone, Default)]
+pub struct HovercrabRayStatus {
+    /// Distance from the bottom of the body to the ground; none if ground isn't
+    /// reached by the ray
+    pub distance: Option<f32>,
+    /// Force applied by the thruster, Newtons
+    pub force: f32,
+}
+
+/// Tuning parameters of the hovercrab.
+///
+/// Each entity can have its own; if entity doesn't have them when [`Hovercrab`]
+/// is added, they are loaded from [`HovercrabTuning`] or default ones are
+/// inserted.
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HovercrabParams {
+    /// Half-extents of the body collider
+    pub half_size: Vec3,
+    /// Kilograms
+    pub mass: f32,
+
+    /// Max turning speed, degrees per second
+    pub rotation_speed_degrees: f32,
+    /// Max turning acceleration, degrees per second squared
+    pub rotation_acceleration_degrees: f32,
+    /// How fast turning speed changes to the desired one, per second
+    pub rotation_damping: f32,
+
+    /// Where hover thrusters are placed; each casts a ray to the ground
+    pub thrusters: ThrusterLayout,
+    /// Horizontal offset of hover rays from the center, relative to `half_size`
+    pub ray_offset: f32,
+    pub ray_margin: f32,
+    pub ray_length: f32,
+    /// How much hover rays are tilted when moving forward or backward, degrees
+    pub max_tilt_degrees: f32,
+
+    /// Spring force of each thruster: acceleration per meter of difference from
+    /// hover height
+    pub thruster_stiffness: f32,
+    /// Damper force of each thruster: acceleration per meter per second of
+    /// velocity along the ray
+    pub thruster_damping: f32,
+    /// Limit of each thruster force, relative to gravity
+    pub max_hover_force: f32,
+
+    /// How strongly body is rotated to align with the ground: angular
+    /// acceleration per radian of difference
+    pub align_stiffness: f32,
+    /// Damping of the rotation around horizontal axes, per second
+    pub align_damping: f32,
+
+    pub aerodynamics: Aerodynamics,
+
+    /// Additional forward acceleration while boosting, meters per second squared
+    pub boost_acceleration: f32,
+    /// Max amount of boost energy
+    pub boost_energy: f32,
+    /// Energy used by boost per second
+    pub boost_energy_usage: f32,
+    /// Energy restored per second while not boosting
+    pub boost_energy_regen: f32,
+
+    /// Deceleration of horizontal movement by air-brake, meters per second
+    /// squared
+    pub brake_acceleration: f32,
+    /// Drag coefficient is multiplied by this while air-brake is applied
+    pub brake_drag_multiplier: f32,
+
+    /// Initial distance from the ground to the bottom of the body
+    pub hover_height: f32,
+    pub min_hover_height: f32,
+    pub max_hover_height: f32,
+    /// How fast player changes hover height, meters per second
+    pub hover_height_speed: f32,
+
+    /// Vertical speed of hop without charging, meters per second
+    pub hop_min_speed: f32,
+    /// Vertical speed of fully charged hop, meters per second
+    pub hop_max_speed: f32,
+    /// Seconds to fully charge hop
+    pub hop_charge_time: f32,
+    /// Seconds after hop during which it can't be charged
+    pub hop_cooldown: f32,
+}
+
+impl Default for HovercrabParams {
+    fn default() -> Self {
         Self {
-            camera_entity: Some(camera_entity),
-            ..default()
+            half_size: Vec3::new(4., 1., 3.),
+            mass: 800.,
+            rotation_speed_degrees: 180.,
+            rotation_acceleration_degrees: 360.,
+            rotation_damping: 10.,
+            thrusters: ThrusterLayout::Corners,
+            ray_offset: 0.8,
+            ray_margin: 0.1,
+            ray_length: 10.,
+            max_tilt_degrees: 45.,
+            thruster_stiffness: 20.,
+            thruster_damping: 9., // a bit more than 2 * sqrt(stiffness), critically damped
+            max_hover_force: 3.,
+            align_stiffness: 30.,
+            align_damping: 11.,
+            aerodynamics: default(),
+            boost_acceleration: 15.,
+            boost_energy: 100.,
+            boost_energy_usage: 40.,
+            boost_energy_regen: 15.,
+            brake_acceleration: 20.,
+            brake_drag_multiplier: 5.,
+            hover_height: 2.,
+            min_hover_height: 0.5,
+            max_hover_height: 6.,
+            hover_height_speed: 2.,
+            hop_min_speed: 4.,
+            hop_max_speed: 12.,
+            hop_charge_time: 1.,
+            hop_cooldown: 2.,
+        }
+    }
+}
+
+impl HovercrabParams {
+    /// Directory with all tuning files
+    pub const DIRECTORY: &'static str = "config/hovercrab";
+
+    /// Path to the tuning file with that name
+    pub fn filename(name: &str) -> String {
+        format!("{}/{name}.ron", Self::DIRECTORY)
+    }
+
+    /// Load tuning by name. If file doesn't exist or is invalid, default values
+    /// are used; file is created if it doesn't exist.
+    pub fn load(name: &str) -> Self {
+        let filename = Self::filename(name);
+        match file_utils::load_valid_ron_file(&filename) {
+            Some(params) => params,
+            None => {
+                let params = Self::default();
+                if std::fs::metadata(&filename).is_err() {
+                    file_utils::save_ron_file(&params, &filename);
+                }
+                params
+            }
+        }
+    }
+
+    /// Save tuning by name. Returns false on error.
+    pub fn save(&self, name: &str) -> bool {
+        file_utils::save_ron_file(self, &Self::filename(name))
+    }
+}
+
+impl Validate for HovercrabParams {
+    fn validate(&self) -> Result<(), String> {
+        self.thrusters.validate()?;
+        self.aerodynamics.validate()?;
+        if !self.half_size.is_finite() {
+            return Err("half_size must be finite".to_string());
+        }
+        file_utils::check_finite([
+            ("mass", self.mass),
+            ("rotation_speed_degrees", self.rotation_speed_degrees),
+            (
+                "rotation_acceleration_degrees",
+                self.rotation_acceleration_degrees,
+            ),
+            ("rotation_damping", self.rotation_damping),
+            ("ray_offset", self.ray_offset),
+            ("ray_margin", self.ray_margin),
+            ("ray_length", self.ray_length),
+            ("max_tilt_degrees", self.max_tilt_degrees),
+            ("thruster_stiffness", self.thruster_stiffness),
+            ("thruster_damping", self.thruster_damping),
+            ("max_hover_force", self.max_hover_force),
+            ("align_stiffness", self.align_stiffness),
+            ("align_damping", self.align_damping),
+            ("boost_acceleration", self.boost_acceleration),
+            ("boost_energy", self.boost_energy),
+            ("boost_energy_usage", self.boost_energy_usage),
+            ("boost_energy_regen", self.boost_energy_regen),
+            ("brake_acceleration", self.brake_acceleration),
+            ("brake_drag_multiplier", self.brake_drag_multiplier),
+            ("hover_height", self.hover_height),
+            ("min_hover_height", self.min_hover_height),
+            ("max_hover_height", self.max_hover_height),
+            ("hover_height_speed", self.hover_height_speed),
+            ("hop_min_speed", self.hop_min_speed),
+            ("hop_max_speed", self.hop_max_speed),
+            ("hop_charge_time", self.hop_charge_time),
+            ("hop_cooldown", self.hop_cooldown),
+        ])?;
+
+        if self.half_size.min_element() <= 0. {
+            return Err("half_size must be positive".to_string());
+        }
+        if self.mass <= 0. {
+            return Err("mass must be positive".to_string());
+        }
+        if self.ray_length <= 0. {
+            return Err("ray_length must be positive".to_string());
+        }
+        if self.min_hover_height > self.max_hover_height {
+            return Err("min_hover_height is greater than max_hover_height".to_string());
+        }
+        if !(self.min_hover_height..=self.max_hover_height).contains(&self.hover_height) {
+            return Err(
+                "hover_height must be between min_hover_height and max_hover_height".to_string(),
+            );
+        }
+        if self.max_hover_height >= self.ray_length {
+            return Err("max_hover_height must be less than ray_length".to_string());
+        }
+        if self.hop_charge_time <= 0. {
+            return Err("hop_charge_time must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Placement of hover thrusters.
+///
+/// Positions are on XZ plane, from -1 to 1, and are scaled by
+/// [`HovercrabParams::half_size`] and [`HovercrabParams::ray_offset`].
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+pub enum ThrusterLayout {
+    /// In the middle of each side
+    Sides,
+    /// In each corner
+    Corners,
+    /// Evenly spaced grid with that many thrusters along X and Z axes
+    Grid { x: u32, z: u32 },
+    /// Arbitrary positions
+    Custom(Vec<Vec2>),
+}
+
+impl ThrusterLayout {
+    /// Positions on XZ plane, from -1 to 1
+    pub fn positions(&self) -> Vec<Vec2> {
+        match self {
+            ThrusterLayout::Sides => vec![
+                Vec2::new(0., -1.), // forward
+                Vec2::new(0., 1.),  // backward
+                Vec2::new(-1., 0.), // left
+                Vec2::new(1., 0.),  // right
+            ],
+            ThrusterLayout::Corners => vec![
+                Vec2::new(-1., -1.),
+                Vec2::new(1., -1.),
+                Vec2::new(-1., 1.),
+                Vec2::new(1., 1.),
+            ],
+            ThrusterLayout::Grid { x, z } => {
+                let coord = |index: u32, count: u32| {
+                    if count > 1 {
+                        index as f32 / (count - 1) as f32 * 2. - 1.
+                    } else {
+                        0.
+                    }
+                };
+                (0..*x)
+                    .flat_map(|ix| (0..*z).map(move |iz| Vec2::new(coord(ix, *x), coord(iz, *z))))
+                    .collect()
+            }
+            ThrusterLayout::Custom(positions) => positions.clone(),
         }
     }
 }
 
+impl Validate for ThrusterLayout {
+    fn validate(&self) -> Result<(), String> {
+        let positions = self.positions();
+        if positions.is_empty() {
+            return Err("there must be at least one thruster".to_string());
+        }
+        if !positions.iter().all(|position| position.is_finite()) {
+            return Err("thruster positions must be finite".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Name of the [`HovercrabParams`] file used by this entity.
+///
+/// Parameters are loaded when [`Hovercrab`] is added and reloaded each time the
+/// file changes.
+#[derive(Component)]
+pub struct HovercrabTuning(pub String);
+
 //
 
 pub struct HovercrabPlugin;
 
 impl Plugin for HovercrabPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (spawn_hovercrab, (hovercrab_input, update_hovercrab).chain()),
-        );
+        app.configure_set(FixedUpdate, HovercrabControlsSet.before(update_hovercrab))
+            .add_systems(
+                Update,
+                (
+                    spawn_hovercrab,
+                    reload_hovercrab_params,
+                    apply_deferred,
+                    (update_hovercrab_body, record_hovercrab_params),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                update_hovercrab.before(PhysicsSet::SyncBackend),
+            )
+            .add_console_command(
+                "spawn hovercrab",
+                "[<x> <y> <z>]",
+                "spawn hovercrab with default tuning",
+                |world, args| {
+                    let position = match args.len() {
+                        0 => Vec3::new(0., 5., 0.),
+                        _ => Vec3::from_array(parse_console_args(args)?),
+                    };
+                    let entity = world
+                        .spawn((
+                            SpatialBundle::from_transform(Transform::from_translation(position)),
+                            Hovercrab,
+                            HovercrabTuning("default".to_string()),
+                        ))
+                        .id();
+                    Ok(format!("Spawned {entity:?} at {position}"))
+                },
+            )
+            .add_console_command(
+                "set",
+                "hovercrab.<field> <value>",
+                "change tuning of all hovercrabs, until reload",
+                |world, args| {
+                    let [path, value] = args else {
+                        return Err("Expected field and value".to_string());
+                    };
+                    let Some(path) = path.strip_prefix("hovercrab.") else {
+                        return Err(format!("Unknown object in \"{path}\""));
+                    };
+
+                    let mut crabs = world.query::<&mut HovercrabParams>();
+                    let mut count = 0;
+                    for mut params in crabs.iter_mut(world) {
+                        let mut new_params = params.clone();
+                        set_reflect_field(new_params.as_reflect_mut(), path, value)?;
+                        new_params.validate()?;
+                        *params = new_params;
+                        count += 1;
+                    }
+                    Ok(format!("Changed {count} hovercrabs"))
+                },
+            );
     }
 }
 
-const CRAB_HALF_SIZE: Vec3 = Vec3::new(4., 1., 3.);
+fn spawn_hovercrab(
+    mut commands: Commands,
+    entities: Query<
+        (
+            Entity,
+            Option<&HovercrabParams>,
+            Option<&HovercrabTuning>,
+            Option<&HovercrabControls>,
+        ),
+        Added<Hovercrab>,
+    >,
+    mut watched_files: ResMut<WatchedFiles>,
+) {
+    for (entity, params, tuning, controls) in entities.iter() {
+        if controls.is_none() {
+            commands.try_insert(entity, HovercrabControls::default());
+        }
 
-fn spawn_hovercrab(mut commands: Commands, entities: Query<Entity, Added<Hovercrab>>) {
-    let half_size = CRAB_HALF_SIZE;
-    let mass = 800.;
+        let params = match (params, tuning) {
+            (Some(params), _) => params.clone(),
+            (None, Some(tuning)) => {
+                // file may be created by loading, that's not a change
+                let params = HovercrabParams::load(&tuning.0);
+                watched_files.watch(&HovercrabParams::filename(&tuning.0));
+                commands.try_insert(entity, params.clone());
+                params
+            }
+            (None, None) => {
+                let params = HovercrabParams::default();
+                commands.try_insert(entity, params.clone());
+                params
+            }
+        };
+        let half_size = params.half_size;
+        let mass = params.mass;
 
-    for entity in entities.iter() {
         commands.try_insert(
             entity,
             (
@@ -51,132 +481,188 @@ fn spawn_hovercrab(mut commands: Commands, entities: Query<Entity, Added<Hovercr
                 ReadMassProperties::default(),
                 Velocity::default(),
                 ExternalForce::default(),
+                ExternalImpulse::default(),
+                HovercrabStatus {
+                    boost_energy: params.boost_energy,
+                    hover_height: params.hover_height,
+                    ground_normal: Vec3::Y,
+                    ..default()
+                },
             ),
         );
     }
 }
 
-// TODO: move this to presentation? untie from keys, tie to local player
-fn hovercrab_input(
-    mut crab: Query<&mut Hovercrab>,
-    cameras: Query<&Transform>,
-    keys: Res<Input<KeyCode>>,
-    // buttons: Res<Input<MouseButton>>,
+fn reload_hovercrab_params(
+    mut crabs: Query<(&HovercrabTuning, &mut HovercrabParams)>,
+    mut changes: EventReader<FileChanged>,
 ) {
-    let Ok(mut crab) = crab.get_single_mut() else {
-        return
-    };
-
-    let rotation = crab
-        .camera_entity
-        .and_then(|e| cameras.get(e).ok())
-        .map(|t| t.rotation)
-        .unwrap_or_default();
-
-    let mut mov = Vec3::ZERO;
-    if keys.pressed(KeyCode::W) {
-        mov.z -= 1.
-    }
-    if keys.pressed(KeyCode::S) {
-        mov.z += 1.
-    }
-    if keys.pressed(KeyCode::A) {
-        mov.x -= 1.
-    }
-    if keys.pressed(KeyCode::D) {
-        mov.x += 1.
+    for change in changes.iter() {
+        for (tuning, mut params) in crabs.iter_mut() {
+            if HovercrabParams::filename(&tuning.0) != change.filename {
+                continue;
+            }
+            let Some(new_params) =
+                file_utils::load_valid_ron_file::<HovercrabParams>(&change.filename)
+            else {
+                continue
+            };
+            info!("Reloaded hovercrab tuning \"{}\"", tuning.0);
+            *params = new_params;
+        }
     }
-    mov = mov.normalize_or_zero();
+}
 
-    if keys.pressed(KeyCode::Z) {
-        mov.y += 1.
+/// Keep current parameters for crash report, since they may differ from the file
+fn record_hovercrab_params(
+    crabs: Query<(Entity, &HovercrabParams), Changed<HovercrabParams>>,
+    mut removed: RemovedComponents<HovercrabParams>,
+) {
+    for entity in removed.iter() {
+        remove_crash_settings(&format!("hovercrab {entity:?}"));
     }
-    if keys.pressed(KeyCode::X) {
-        mov.y -= 1.
+    for (entity, params) in crabs.iter() {
+        set_crash_settings(&format!("hovercrab {entity:?}"), params);
     }
+}
 
-    crab.input_dir = mov;
-
-    crab.input_accel = keys.pressed(KeyCode::ShiftLeft);
-    crab.input_stop = keys.pressed(KeyCode::ControlLeft);
+/// Apply changes to mass and size to the physics body
+fn update_hovercrab_body(
+    mut crabs: Query<
+        (&HovercrabParams, &mut Collider, &mut ColliderMassProperties),
+        Changed<HovercrabParams>,
+    >,
+) {
+    for (params, mut collider, mut mass) in crabs.iter_mut() {
+        let half_size = params.half_size;
 
-    crab.target_rotation = rotation * Vec3::NEG_Z;
+        // don't reset physics state if nothing changed
+        let same_size = collider
+            .as_cuboid()
+            .is_some_and(|cuboid| cuboid.half_extents() == half_size);
+        if !same_size {
+            *collider = Collider::cuboid(half_size.x, half_size.y, half_size.z);
+        }
+        if *mass != ColliderMassProperties::Mass(params.mass) {
+            *mass = ColliderMassProperties::Mass(params.mass);
+        }
+    }
 }
 
 fn update_hovercrab(
     mut crabs: Query<(
         Entity,
-        &Hovercrab,
+        &HovercrabControls,
+        &HovercrabParams,
         &Transform,
         &Velocity,
         &ReadMassProperties,
         &mut ExternalForce,
+        &mut ExternalImpulse,
+        &mut HovercrabStatus,
     )>,
     phy_ctx: Res<RapierContext>,
     phy_config: Res<RapierConfiguration>,
-    time: Res<Time>,
+    atmosphere: Res<Atmosphere>,
+    time: Res<FixedTime>,
 ) {
-    let rotation_speed = 180_f32.to_radians();
-    let body_height = CRAB_HALF_SIZE;
-    let ray_max_offset = CRAB_HALF_SIZE * 0.8;
-    let ray_margin = 0.1;
-    let ray_length = 10.;
+    let _span = info_span!("update_hovercrab").entered();
 
-    let delta_seconds = time.delta_seconds();
-    if delta_seconds < 0.001 {
-        return;
-    }
+    let delta_seconds = time.period.as_secs_f32();
 
-    for (body_entity, crab, transform, velocity, mass, mut ext_force) in crabs.iter_mut() {
-        let mass = mass.0.mass;
+    for (
+        body_entity,
+        controls,
+        params,
+        transform,
+        velocity,
+        mass_properties,
+        mut ext_force,
+        mut ext_impulse,
+        mut status,
+    ) in crabs.iter_mut()
+    {
+        let _span = debug_span!("hovercrab", entity = ?body_entity).entered();
+
+        let rotation_speed = params.rotation_speed_degrees.to_radians();
+        let rotation_acceleration = params.rotation_acceleration_degrees.to_radians();
+        let body_height = params.half_size;
+        let ray_max_offset = params.half_size * params.ray_offset;
+        let ray_margin = params.ray_margin;
+        let ray_length = params.ray_length;
+
+        let mass = mass_properties.0.mass;
         let center_of_mass = transform.translation;
         let body_rotation = transform.rotation;
 
         // reset forces
         *ext_force = default();
 
-        // rotation magic
-        {
-            let current_dir = (body_rotation * Vec3::NEG_Z).xz();
-            let target_dir = crab.target_rotation.xz();
+        // yaw controller
+        if controls.target_rotation.xz().length_squared() > 1e-6 {
+            let yaw = |dir: Vec3| (-dir.x).atan2(-dir.z); // around +Y, zero at -Z
 
-            let current_velocity = -velocity.angvel.y;
+            let delta_angle = math_algorithms::shortest_angle(
+                yaw(body_rotation * Vec3::NEG_Z),
+                yaw(controls.target_rotation),
+            );
+            let current_velocity = velocity.angvel.y;
 
-            let next_angle = Vec2::Y.angle_between(current_dir) + current_velocity * delta_seconds;
-            let target_angle = Vec2::Y.angle_between(target_dir);
-            let delta_angle = math_algorithms::shortest_angle(next_angle, target_angle);
+            // decelerate slower than max, so there is some headroom for damping
+            let target_velocity = math_algorithms::approach_speed(
+                delta_angle,
+                rotation_speed,
+                rotation_acceleration * 0.5,
+            );
+            // don't overshoot in one step
+            let target_velocity = target_velocity
+                .abs()
+                .min(delta_angle.abs() / delta_seconds)
+                .copysign(target_velocity);
 
-            let target_velocity = delta_angle / delta_seconds;
-            // let target_velocity = target_velocity
-            //     .abs()
-            //     .min(rotation_speed)
-            //     .copysign(target_velocity);
-            let torque = target_velocity / delta_seconds;
+            let acceleration = ((target_velocity - current_velocity) * params.rotation_damping)
+                .clamp(-rotation_acceleration, rotation_acceleration);
 
-            ext_force.torque.y -= torque * mass; // * 0.5;
+            let inertia = math_algorithms::inertia_around_axis(
+                mass_properties.0.principal_inertia,
+                body_rotation * mass_properties.0.principal_inertia_local_frame,
+                Vec3::Y,
+            );
+            ext_force.torque.y += acceleration * inertia;
         }
 
+        // hover height
+        status.hover_height = (status.hover_height
+            + controls.movement.y * params.hover_height_speed * delta_seconds)
+            .clamp(params.min_hover_height, params.max_hover_height);
+
+        // for hop
+        let mut ground_dir = Vec3::ZERO;
+        // for alignment
+        let mut normal_sum = Vec3::ZERO;
+
+        // rays are relative to the ground from the previous step
+        let ground_rotation = Quat::from_rotation_arc(Vec3::Y, status.ground_normal);
+
         // rays
-        let ray_count = 4.;
-        for (x_dir, z_dir) in [
-            (0., -1.), // forward
-            (0., 1.),  // backward
-            (-1., 0.), // left
-            (1., 0.),  // right
-        ] {
-            let ray_pos =
-                center_of_mass + body_rotation * (Vec3::new(x_dir, 0., z_dir) * ray_max_offset);
-
-            // let ray_dir = Vec3::Y;
+        let thrusters = params.thrusters.positions();
+        let ray_count = thrusters.len() as f32;
+        status.rays.clear();
+        for thruster in thrusters {
+            let ray_pos = center_of_mass
+                + body_rotation * (Vec3::new(thruster.x, 0., thruster.y) * ray_max_offset);
+
             let ray_dir = {
                 // v.3
 
-                let max_angle = 45f32.to_radians();
+                let max_angle = params.max_tilt_degrees.to_radians();
                 let rotation = math_algorithms::quat_component(transform.rotation, Vec3::Y);
 
-                let fwd_rotation = Quat::from_rotation_x(crab.input_dir.z * max_angle);
+                let tilt = Quat::from_scaled_axis(
+                    Vec3::new(controls.movement.z, 0., -controls.movement.x) * max_angle,
+                );
 
-                let rotation = rotation * fwd_rotation;
+                let rotation = ground_rotation * rotation * tilt;
 
                 rotation * Vec3::Y
 
@@ -189,12 +675,12 @@ fn update_hovercrab(
                 // let side = rotation * Vec3::X;
 
                 // let t = 0.25; // TODO: magic
-                // let t = if crab.input_dir.x.abs() + crab.input_dir.z.abs() > 1.9 {
+                // let t = if controls.movement.x.abs() + controls.movement.z.abs() > 1.9 {
                 //     t / 2.
                 // } else {
                 //     t
                 // };
-                // (base + fwd * -crab.input_dir.z * t + side * t * crab.input_dir.x).normalize()
+                // (base + fwd * -controls.movement.z * t + side * t * controls.movement.x).normalize()
 
                 // v.1
 
@@ -209,7 +695,7 @@ fn update_hovercrab(
             let ray_offset = ray_margin * 0.5;
             let body_offset = -ray_dir * body_height;
 
-            let ray_hit = phy_ctx.cast_ray(
+            let ray_hit = phy_ctx.cast_ray_and_get_normal(
                 ray_pos + ray_dir * ray_offset + body_offset,
                 -ray_dir,
                 ray_length + ray_margin,
@@ -218,47 +704,432 @@ fn update_hovercrab(
             );
 
             let gravity = phy_config.gravity.y.abs();
-            let k_force = 50.; // magic
-            let min_force = gravity;
-            let max_hover_force = 2. * gravity; // magic
+            let max_hover_force = params.max_hover_force * gravity;
 
-            let mut force = min_force;
+            let mut force = 0.;
 
-            // hover magic
-            if let Some((_hit_entity, hit_distance)) = ray_hit {
-                let distance_factor = (ray_length - hit_distance).max(0.) / ray_length;
+            // spring-damper
+            if let Some((_hit_entity, hit)) = ray_hit {
+                let hit_distance = hit.toi;
+                normal_sum += hit.normal;
 
                 let current_velocity = velocity
                     .linear_velocity_at_point(ray_pos, center_of_mass)
-                    .project_onto(ray_dir)
-                    .y
-                    .min(0.);
-                let target_velocity = 0.;
-                let target_force =
-                    (target_velocity - current_velocity).max(0.) * distance_factor * k_force
-                        / delta_seconds;
-
-                force += target_force.clamp(-max_hover_force, max_hover_force);
+                    .dot(ray_dir);
+
+                let spring = (status.hover_height - hit_distance) * params.thruster_stiffness;
+                let damper = -current_velocity * params.thruster_damping;
+
+                // thrusters can only push
+                force = (gravity + spring + damper).clamp(0., max_hover_force);
+
+                if hit_distance <= params.max_hover_height + ray_margin {
+                    ground_dir += ray_dir;
+                }
             }
 
-            *ext_force += ExternalForce::at_point(
-                ray_dir * force * mass / ray_count,
-                ray_pos,
-                center_of_mass,
-            );
+            let force = force * mass / ray_count;
+            *ext_force += ExternalForce::at_point(ray_dir * force, ray_pos, center_of_mass);
+
+            status.rays.push(HovercrabRayStatus {
+                distance: ray_hit.map(|(_, hit)| hit.toi),
+                force,
+            });
         }
 
-        // air drag force (real formula)
+        status.ground_normal = normal_sum.try_normalize().unwrap_or(Vec3::Y);
+
+        // align with the ground
+        {
+            let body_up = body_rotation * Vec3::Y;
+            let axis = body_up.cross(status.ground_normal).normalize_or_zero();
+            let angle = body_up.angle_between(status.ground_normal);
+
+            // yaw is handled separately
+            let tilt_velocity = velocity.angvel.reject_from(body_up);
+
+            let acceleration =
+                axis * angle * params.align_stiffness - tilt_velocity * params.align_damping;
+
+            if let Some(direction) = acceleration.try_normalize() {
+                let inertia = math_algorithms::inertia_around_axis(
+                    mass_properties.0.principal_inertia,
+                    body_rotation * mass_properties.0.principal_inertia_local_frame,
+                    direction,
+                );
+                ext_force.torque += acceleration * inertia;
+            }
+        }
+
+        // hop
+        {
+            status.hop_cooldown = (status.hop_cooldown - delta_seconds).max(0.);
+
+            if controls.hop && status.hop_cooldown <= 0. {
+                status.hop_charge =
+                    (status.hop_charge + delta_seconds / params.hop_charge_time).min(1.);
+            } else if status.hop_charge > 0. {
+                // released; works only near the ground
+                if ground_dir != Vec3::ZERO {
+                    let speed = math_algorithms::lerp(
+                        params.hop_min_speed,
+                        params.hop_max_speed,
+                        status.hop_charge,
+                    );
+                    ext_impulse.impulse += ground_dir.normalize() * speed * mass;
+                    status.hop_cooldown = params.hop_cooldown;
+                }
+                status.hop_charge = 0.;
+            }
+        }
+
+        // boost
         {
-            let speed = velocity.linvel.length();
-            let dir = velocity.linvel.normalize_or_zero();
+            let boost = controls.boost.clamp(0., 1.);
+            let energy_usage = params.boost_energy_usage * boost * delta_seconds;
+            status.boosting = boost > 0. && status.boost_energy >= energy_usage;
+
+            if status.boosting {
+                status.boost_energy -= energy_usage;
+
+                let forward = (body_rotation * Vec3::NEG_Z)
+                    .reject_from(Vec3::Y)
+                    .normalize_or_zero();
+                ext_force.force += forward * params.boost_acceleration * boost * mass;
+            } else {
+                status.boost_energy = (status.boost_energy
+                    + params.boost_energy_regen * delta_seconds)
+                    .min(params.boost_energy);
+            }
+        }
+
+        // air-brake
+        status.braking = controls.brake;
+        if status.braking {
+            let horizontal_velocity = velocity.linvel.reject_from(Vec3::Y);
+            let speed = horizontal_velocity.length();
+
+            // don't reverse direction of movement
+            let deceleration = params.brake_acceleration.min(speed / delta_seconds);
+            ext_force.force -= horizontal_velocity.normalize_or_zero() * deceleration * mass;
+        }
+
+        // aerodynamics
+        {
+            let aero = &params.aerodynamics;
+            let air_velocity = velocity.linvel - atmosphere.wind;
+            let air_density = atmosphere.air_density;
+
+            let drag = aero.drag(body_rotation, air_velocity, air_density);
+            let drag = if status.braking {
+                drag * params.brake_drag_multiplier
+            } else {
+                drag
+            };
+
+            ext_force.force += drag + aero.lift(body_rotation, air_velocity, air_density);
+            ext_force.torque +=
+                aero.angular_damping_torque(body_rotation, velocity.angvel, &mass_properties.0);
+        }
+
+        status.torque = ext_force.torque;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{gameplay::physics::PhysicsPlugin, headless};
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    /// Fixed updates run so far
+    #[derive(Resource, Default)]
+    struct FixedSteps(usize);
+
+    /// Same as [`FixedTime::period`] set by [`PhysicsPlugin`]
+    fn physics_period() -> Duration {
+        Duration::from_secs_f32(1. / PhysicsPlugin::default().updates_per_second)
+    }
+
+    /// Headless app with single hovercrab on flat ground, each frame takes
+    /// `frame_time`. Physics body already exists when this returns.
+    fn simulation_app(start: Vec3, frame_time: Duration) -> (App, Entity) {
+        simulation_app_with_ground(headless::flat_ground(), start, frame_time)
+    }
+
+    /// Same as [`simulation_app`], but with specified ground
+    fn simulation_app_with_ground(
+        ground: impl Bundle,
+        start: Vec3,
+        frame_time: Duration,
+    ) -> (App, Entity) {
+        let mut app = headless::headless_app();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(frame_time))
+            .init_resource::<FixedSteps>()
+            .add_systems(FixedUpdate, |mut steps: ResMut<FixedSteps>| steps.0 += 1);
+
+        app.world.spawn(ground);
+        let crab = app
+            .world
+            .spawn((
+                Hovercrab,
+                TransformBundle::from_transform(Transform::from_translation(start)),
+            ))
+            .id();
+
+        app.update();
+        (app, crab)
+    }
+
+    /// Run frames until that many fixed updates happen
+    fn run_fixed_steps(app: &mut App, steps: usize) {
+        let target = app.world.resource::<FixedSteps>().0 + steps;
+        while app.world.resource::<FixedSteps>().0 < target {
+            app.update();
+        }
+    }
+
+    fn fixed_steps_for(seconds: f32) -> usize {
+        (seconds / physics_period().as_secs_f32()).round() as usize
+    }
+
+    fn state(app: &App, crab: Entity) -> (Transform, Velocity) {
+        let crab = app.world.entity(crab);
+        (
+            *crab.get::<Transform>().unwrap(),
+            *crab.get::<Velocity>().unwrap(),
+        )
+    }
+
+    /// Simulate single hovercrab on flat ground with constant controls.
+    ///
+    /// Returns final state.
+    fn simulate_with_controls(
+        start: Vec3,
+        seconds: f32,
+        controls: HovercrabControls,
+    ) -> (Transform, Velocity) {
+        let (mut app, crab) = simulation_app(start, physics_period());
+        *app.world.get_mut::<HovercrabControls>(crab).unwrap() = controls;
+        run_fixed_steps(&mut app, fixed_steps_for(seconds));
+        state(&app, crab)
+    }
+
+    /// Simulate single hovercrab on flat ground without controls.
+    ///
+    /// Returns final state.
+    fn simulate(start: Vec3, seconds: f32) -> (Transform, Velocity) {
+        simulate_with_controls(start, seconds, default())
+    }
+
+    #[test]
+    fn thruster_grid() {
+        let positions = ThrusterLayout::Grid { x: 3, z: 2 }.positions();
 
-            let air_density = 1.225;
-            let area = CRAB_HALF_SIZE.x * CRAB_HALF_SIZE.y * 4.;
-            let drag_coeff = 0.09; // depends on object shape
-            let force = 0.5 * air_density * speed.powi(2) * area * drag_coeff;
+        assert_eq!(positions.len(), 6);
+        assert!(positions.contains(&Vec2::new(-1., -1.)));
+        assert!(positions.contains(&Vec2::new(0., 1.)));
+        assert!(positions.contains(&Vec2::new(1., 1.)));
 
-            ext_force.force -= dir * force;
+        let positions = ThrusterLayout::Grid { x: 1, z: 1 }.positions();
+        assert_eq!(positions, vec![Vec2::ZERO]);
+
+        assert!(ThrusterLayout::Grid { x: 0, z: 4 }.validate().is_err());
+    }
+
+    #[test]
+    fn validate_params() {
+        let invalid = |change: fn(&mut HovercrabParams)| {
+            let mut params = HovercrabParams::default();
+            change(&mut params);
+            params.validate().is_err()
+        };
+
+        assert!(HovercrabParams::default().validate().is_ok());
+        assert!(invalid(|params| params.mass = f32::NAN));
+        assert!(invalid(|params| params.half_size.y = f32::NAN));
+        assert!(invalid(|params| params.boost_energy = f32::INFINITY));
+        assert!(invalid(|params| params.aerodynamics.lift_coeff = f32::NAN));
+        assert!(invalid(|params| params.hop_charge_time = 0.));
+        assert!(invalid(
+            |params| params.hover_height = params.max_hover_height + 1.
+        ));
+        assert!(invalid(
+            |params| params.hover_height = params.min_hover_height - 0.1
+        ));
+    }
+
+    #[test]
+    fn hovers_above_ground() {
+        let params = HovercrabParams::default();
+        let (transform, velocity) = simulate(Vec3::new(0., 5., 0.), 10.);
+
+        // rays start slightly above the bottom of the body
+        let height = params.half_size.y + params.hover_height;
+        assert!((transform.translation.y - height).abs() < params.ray_margin);
+        assert!(velocity.linvel.y.abs() < 0.05);
+    }
+
+    #[test]
+    fn hovers_without_bobbing() {
+        let (mut app, crab) = simulation_app(Vec3::new(0., 5., 0.), physics_period());
+        run_fixed_steps(&mut app, fixed_steps_for(4.));
+
+        let heights: Vec<_> = (0..fixed_steps_for(1.))
+            .map(|_| {
+                run_fixed_steps(&mut app, 1);
+                state(&app, crab).0.translation.y
+            })
+            .collect();
+        let min = heights.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = heights.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        assert!(max - min < 0.01, "oscillation is {} m", max - min);
+    }
+
+    #[test]
+    fn same_trajectory_at_any_frame_rate() {
+        let period = physics_period();
+        let controls = HovercrabControls {
+            movement: Vec3::new(0.5, 0., -1.).clamp_length_max(1.),
+            boost: 1.,
+            target_rotation: Vec3::X,
+            ..default()
+        };
+
+        // 30, 60 and 144 frames per second; frame is either a multiple of
+        // the period or shorter, so exactly that many steps are run
+        let states: Vec<_> = [period * 2, period, period * 5 / 12]
+            .into_iter()
+            .map(|frame_time| {
+                let (mut app, crab) = simulation_app(Vec3::new(0., 5., 0.), frame_time);
+                *app.world.get_mut::<HovercrabControls>(crab).unwrap() = controls.clone();
+                run_fixed_steps(&mut app, fixed_steps_for(5.));
+                state(&app, crab)
+            })
+            .collect();
+
+        for (transform, velocity) in &states[1..] {
+            assert_eq!(*transform, states[0].0);
+            assert_eq!(*velocity, states[0].1);
+        }
+    }
+
+    #[test]
+    fn despawn_without_fixed_update() {
+        // several frames per physics step
+        let (mut app, crab) = simulation_app(Vec3::new(0., 5., 0.), physics_period() / 8);
+        run_fixed_steps(&mut app, 10);
+
+        let bodies = |app: &App| app.world.resource::<RapierContext>().entity2body().len();
+        let bodies_before = bodies(&app);
+        let steps_before = app.world.resource::<FixedSteps>().0;
+
+        // removal events expire before the next fixed update
+        app.world.despawn(crab);
+        for _ in 0..4 {
+            app.update();
         }
+        assert_eq!(app.world.resource::<FixedSteps>().0, steps_before);
+
+        let context = app.world.resource::<RapierContext>();
+        assert!(!context.entity2body().contains_key(&crab));
+        assert_eq!(bodies(&app), bodies_before - 1);
+
+        run_fixed_steps(&mut app, 2);
+        assert_eq!(bodies(&app), bodies_before - 1);
+    }
+
+    /// Height of the center when hovering at rest with default params
+    fn hover_start() -> Vec3 {
+        let params = HovercrabParams::default();
+        Vec3::new(0., params.half_size.y + params.hover_height, 0.)
+    }
+
+    #[test]
+    fn boost_uses_and_restores_energy() {
+        let params = HovercrabParams::default();
+        let (mut app, crab) = simulation_app(hover_start(), physics_period());
+
+        app.world.get_mut::<HovercrabControls>(crab).unwrap().boost = 1.;
+        run_fixed_steps(&mut app, fixed_steps_for(1.));
+
+        let status = app.world.get::<HovercrabStatus>(crab).unwrap();
+        let expected = params.boost_energy - params.boost_energy_usage;
+        assert!(status.boosting);
+        assert!((status.boost_energy - expected).abs() < 1.);
+        // forward is -Z
+        assert!(state(&app, crab).1.linvel.z < -5.);
+
+        app.world.get_mut::<HovercrabControls>(crab).unwrap().boost = 0.;
+        run_fixed_steps(&mut app, fixed_steps_for(1.));
+
+        let status = app.world.get::<HovercrabStatus>(crab).unwrap();
+        let expected = expected + params.boost_energy_regen;
+        assert!(!status.boosting);
+        assert!((status.boost_energy - expected).abs() < 1.);
+    }
+
+    #[test]
+    fn brake_stops_movement() {
+        let speed_after = |brake: bool| {
+            let (mut app, crab) = simulation_app(hover_start(), physics_period());
+            app.world.get_mut::<Velocity>(crab).unwrap().linvel = Vec3::new(0., 0., -20.);
+            app.world.get_mut::<HovercrabControls>(crab).unwrap().brake = brake;
+            run_fixed_steps(&mut app, fixed_steps_for(1.5));
+            state(&app, crab).1.linvel.reject_from(Vec3::Y).length()
+        };
+
+        assert!(speed_after(false) > 15.);
+        assert!(speed_after(true) < 1.);
+    }
+
+    #[test]
+    fn hover_height_is_adjusted_within_limits() {
+        let params = HovercrabParams::default();
+        let controls = HovercrabControls {
+            movement: Vec3::Y,
+            ..default()
+        };
+        let (transform, _) = simulate_with_controls(hover_start(), 5., controls);
+
+        let altitude = transform.translation.y - params.half_size.y;
+        assert!((altitude - params.max_hover_height).abs() < 0.5);
+    }
+
+    #[test]
+    fn hop_after_charging() {
+        let max_height = |hop: bool| {
+            let (mut app, crab) = simulation_app(hover_start(), physics_period());
+            run_fixed_steps(&mut app, fixed_steps_for(1.));
+
+            app.world.get_mut::<HovercrabControls>(crab).unwrap().hop = hop;
+            run_fixed_steps(&mut app, fixed_steps_for(0.5));
+            app.world.get_mut::<HovercrabControls>(crab).unwrap().hop = false;
+
+            let mut max_height: f32 = 0.;
+            for _ in 0..fixed_steps_for(1.) {
+                run_fixed_steps(&mut app, 1);
+                max_height = max_height.max(state(&app, crab).0.translation.y);
+            }
+            max_height
+        };
+
+        assert!(max_height(true) > max_height(false) + 1.);
+    }
+
+    #[test]
+    fn aligns_with_slope() {
+        let slope = Quat::from_rotation_x(0.3);
+        let ground = (
+            TransformBundle::from_transform(Transform::from_xyz(0., -1., 0.).with_rotation(slope)),
+            RigidBody::Fixed,
+            Collider::cuboid(500., 1., 500.),
+        );
+        let (mut app, crab) = simulation_app_with_ground(ground, hover_start(), physics_period());
+        run_fixed_steps(&mut app, fixed_steps_for(3.));
+
+        let body_up = state(&app, crab).0.rotation * Vec3::Y;
+        assert!(body_up.angle_between(slope * Vec3::Y) < 0.1);
     }
 }
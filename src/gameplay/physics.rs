@@ -0,0 +1,185 @@
+//! Physics simulation with fixed timestep
+
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_rapier3d::prelude::*;
+
+use crate::utils::{
+    for_crate::bevy::FallibleCommands,
+    plugins::console::{parse_console_args, ConsoleApp},
+};
+
+/// Runs physics in [`FixedUpdate`] schedule.
+///
+/// All systems which apply forces should also run in [`FixedUpdate`], before
+/// [`PhysicsSet::SyncBackend`], and use [`FixedTime::period`] as time delta.
+///
+/// Despawned bodies and colliders are also removed from the physics world at
+/// the end of each frame.
+pub struct PhysicsPlugin {
+    pub updates_per_second: f32,
+}
+
+impl Default for PhysicsPlugin {
+    fn default() -> Self {
+        Self {
+            updates_per_second: 60.,
+        }
+    }
+}
+
+/// Transform of a dynamic rigid body, interpolated between two last physics
+/// updates.
+///
+/// Should be used for presentation instead of [`Transform`], so movement is
+/// smooth even if framerate differs from physics update rate. Added
+/// automatically; updated in [`InterpolationSet`].
+#[derive(Component, Default)]
+pub struct InterpolatedTransform {
+    previous: Option<Transform>,
+    current: Transform,
+    pub value: Transform,
+}
+
+impl InterpolatedTransform {
+    /// Discard previous state, so there is no interpolation from old position
+    /// (i.e. after teleport)
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
+
+/// Updates [`InterpolatedTransform`], in [`PostUpdate`] before transform
+/// propagation.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct InterpolationSet;
+
+type RapierPlugin = RapierPhysicsPlugin<()>;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        let delta_seconds = 1. / self.updates_per_second;
+
+        app.add_plugins(RapierPlugin::default().with_default_system_setup(false))
+            .insert_resource(FixedTime::new_from_secs(delta_seconds))
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: delta_seconds,
+                    substeps: 1,
+                },
+                ..default()
+            })
+            .configure_sets(
+                FixedUpdate,
+                (
+                    PhysicsSet::SyncBackend,
+                    PhysicsSet::SyncBackendFlush,
+                    PhysicsSet::StepSimulation,
+                    PhysicsSet::Writeback,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    RapierPlugin::get_systems(PhysicsSet::SyncBackend)
+                        .in_set(PhysicsSet::SyncBackend),
+                    RapierPlugin::get_systems(PhysicsSet::SyncBackendFlush)
+                        .in_set(PhysicsSet::SyncBackendFlush),
+                    RapierPlugin::get_systems(PhysicsSet::StepSimulation)
+                        .in_set(PhysicsSet::StepSimulation),
+                    RapierPlugin::get_systems(PhysicsSet::Writeback).in_set(PhysicsSet::Writeback),
+                    record_physics_transform.after(PhysicsSet::Writeback),
+                ),
+            )
+            // removal events expire after two frames, so they would be lost if
+            // several frames pass without fixed update
+            .add_systems(Last, bevy_rapier3d::plugin::systems::sync_removals)
+            .configure_set(
+                PostUpdate,
+                InterpolationSet.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                PostUpdate,
+                (add_interpolation, apply_deferred, interpolate_transform)
+                    .chain()
+                    .in_set(InterpolationSet),
+            )
+            .add_console_command(
+                "timescale",
+                "<value>",
+                "speed of the game time, 1 is normal",
+                |world, args| {
+                    let [value] = parse_console_args(args)?;
+                    if value <= 0. || !value.is_finite() {
+                        return Err("Time scale must be positive".to_string());
+                    }
+                    world.resource_mut::<Time>().set_relative_speed(value);
+                    Ok(format!("Time scale is {value}"))
+                },
+            )
+            .add_console_command(
+                "physics debug",
+                "on|off",
+                "show colliders",
+                |world, args| {
+                    let enabled = match args {
+                        ["on"] => true,
+                        ["off"] => false,
+                        _ => return Err("Expected \"on\" or \"off\"".to_string()),
+                    };
+                    world
+                        .get_resource_mut::<DebugRenderContext>()
+                        .ok_or("Physics debug rendering isn't available")?
+                        .enabled = enabled;
+                    Ok(String::new())
+                },
+            );
+    }
+}
+
+fn add_interpolation(
+    bodies: Query<(Entity, &RigidBody, &Transform), Added<RigidBody>>,
+    mut commands: Commands,
+) {
+    for (entity, body, transform) in bodies.iter() {
+        if *body == RigidBody::Dynamic {
+            commands.try_insert(
+                entity,
+                InterpolatedTransform {
+                    previous: None,
+                    current: *transform,
+                    value: *transform,
+                },
+            );
+        }
+    }
+}
+
+fn record_physics_transform(mut bodies: Query<(&mut InterpolatedTransform, &Transform)>) {
+    for (mut interpolated, transform) in bodies.iter_mut() {
+        interpolated.previous = Some(interpolated.current);
+        interpolated.current = *transform;
+    }
+}
+
+fn interpolate_transform(
+    mut bodies: Query<(&mut InterpolatedTransform, &Transform)>,
+    fixed_time: Res<FixedTime>,
+) {
+    let t = (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).min(1.);
+
+    for (mut interpolated, transform) in bodies.iter_mut() {
+        let Some(previous) = interpolated.previous else {
+            interpolated.current = *transform;
+            interpolated.value = *transform;
+            continue;
+        };
+        let current = interpolated.current;
+
+        interpolated.value = Transform {
+            translation: previous.translation.lerp(current.translation, t),
+            rotation: previous.rotation.slerp(current.rotation, t),
+            scale: previous.scale.lerp(current.scale, t),
+        };
+    }
+}
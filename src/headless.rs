@@ -0,0 +1,114 @@
+//! Simulation without window, rendering and presentation
+
+use crate::{
+    gameplay::{self, objects::hovercrab::Hovercrab},
+    utils,
+};
+use bevy::{
+    app::{AppExit, ScheduleRunnerPlugin},
+    prelude::*,
+    scene::ScenePlugin,
+};
+use bevy_rapier3d::prelude::*;
+use std::time::Duration;
+
+/// App with only gameplay and physics, running at 60 updates per second.
+///
+/// Physics has fixed timestep, see [`gameplay::physics::PhysicsPlugin`].
+///
+/// Nothing is spawned; settings aren't loaded from files, so the result doesn't
+/// depend on them. Time advances by real time (see
+/// [`bevy::time::TimeUpdateStrategy`] to change that).
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1. / 60.,
+        ))),
+        TransformPlugin,
+        HierarchyPlugin,
+        InputPlugin,
+        AssetPlugin::default(),
+        ScenePlugin,
+        gameplay::physics::PhysicsPlugin::default(),
+        utils::plugins::UtilPlugins,
+        gameplay::GameplayPlugin,
+    ))
+    .insert_resource(gameplay::aerodynamics::Atmosphere::default())
+    // required by physics and scene utils
+    .add_asset::<Mesh>();
+    app
+}
+
+/// Simulation with single hovercrab on flat ground, position is logged every
+/// second.
+pub struct HeadlessSimulationPlugin {
+    /// Exit after that much time passes
+    pub duration: Option<Duration>,
+}
+
+impl Plugin for HeadlessSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        let duration = self.duration;
+
+        app.add_plugins((
+            crate::utils::plugins::logging::LoggingPlugin::default(),
+            crate::utils::plugins::crash_report::CrashReportPlugin,
+        ))
+        .add_systems(Startup, spawn_flat_world)
+        .add_systems(
+            Update,
+            (
+                log_hovercrabs,
+                move |time: Res<Time>, mut exit: EventWriter<AppExit>| {
+                    if duration.map(|d| time.elapsed() > d).unwrap_or(false) {
+                        exit.send_default()
+                    }
+                },
+            ),
+        );
+    }
+}
+
+/// Run [`HeadlessSimulationPlugin`]
+pub fn run(duration: Option<Duration>) {
+    headless_app()
+        .add_plugins(HeadlessSimulationPlugin { duration })
+        .run()
+}
+
+/// Flat ground with top surface at Y = 0
+pub fn flat_ground() -> impl Bundle {
+    (
+        TransformBundle::from_transform(Transform::from_xyz(0., -1., 0.)),
+        RigidBody::Fixed,
+        Collider::cuboid(500., 1., 500.),
+    )
+}
+
+fn spawn_flat_world(mut commands: Commands) {
+    commands.spawn(flat_ground());
+    commands.spawn((
+        Hovercrab,
+        TransformBundle::from_transform(Transform::from_xyz(0., 5., 0.)),
+    ));
+}
+
+fn log_hovercrabs(
+    crabs: Query<(Entity, &Transform, &Velocity), With<Hovercrab>>,
+    time: Res<Time>,
+    mut next_log: Local<f32>,
+) {
+    let now = time.elapsed_seconds();
+    if now < *next_log {
+        return;
+    }
+    *next_log = now + 1.;
+
+    for (entity, transform, velocity) in crabs.iter() {
+        info!(
+            "{:?}: position {:.2}, velocity {:.2}",
+            entity, transform.translation, velocity.linvel
+        );
+    }
+}
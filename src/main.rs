@@ -1,35 +1,48 @@
-use bevy::{
-    prelude::*,
-    window::{PresentMode, WindowMode},
-};
+use bevy::{log::LogPlugin, prelude::*};
 use bevy_egui::EguiPlugin;
 use bevy_mod_mipmap_generator::{generate_mipmaps, MipmapGeneratorPlugin, MipmapGeneratorSettings};
-use bevy_rapier3d::{prelude::RapierPhysicsPlugin, render::RapierDebugRenderPlugin};
+use bevy_rapier3d::render::RapierDebugRenderPlugin;
+use gameplay::aerodynamics::Atmosphere;
+use presentation::graphics::GraphicsSettings;
+use utils::plugins::file_watcher::WatchedRonResourceApp;
 
 mod gameplay;
+mod headless;
 mod presentation;
 mod tmp;
 mod utils;
 
+/// Command-line arguments:
+///
+/// - `--headless` - run only gameplay and physics, without window
+/// - `--seconds <N>` - with `--headless`, exit after N seconds
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.iter().any(|arg| arg == "--headless") {
+        let duration = args
+            .iter()
+            .position(|arg| arg == "--seconds")
+            .and_then(|index| args.get(index + 1))
+            .and_then(|value| value.parse().ok())
+            .map(std::time::Duration::from_secs_f32);
+        return headless::run(duration);
+    }
+
     App::new()
         .add_plugins((
-            DefaultPlugins.set(WindowPlugin {
-                primary_window: Some(Window {
-                    present_mode: PresentMode::Fifo,
-                    mode: WindowMode::Windowed,
-                    position: WindowPosition::At(IVec2::ZERO),
-                    resolution: (1920., 1080.).into(),
-                    title: "Hovercrab".to_string(),
-                    resizable: false,
+            utils::plugins::logging::LoggingPlugin::default(),
+            utils::plugins::crash_report::CrashReportPlugin,
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(GraphicsSettings::load().window()),
                     ..default()
-                }),
-                ..default()
-            }),
+                })
+                .disable::<LogPlugin>(),
             MipmapPlugin {
                 anisotropic_filtering: 16,
             },
-            RapierPhysicsPlugin::<()>::default(),
+            gameplay::physics::PhysicsPlugin::default(),
             RapierDebugRenderPlugin {
                 enabled: true,
                 ..default()
@@ -40,6 +53,7 @@ fn main() {
             gameplay::GameplayPlugin,
             presentation::PresentationPlugin,
         ))
+        .add_watched_ron_resource::<Atmosphere>(Atmosphere::FILENAME)
         .insert_resource(GizmoConfig {
             // depth_bias: -1.,
             ..default()
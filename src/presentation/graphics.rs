@@ -0,0 +1,120 @@
+//! Window and rendering settings
+
+use crate::utils::{
+    file_utils::{self, Validate},
+    plugins::settings::{settings_filename, SettingsApp},
+};
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    /// Changes video mode of the monitor to the resolution
+    Fullscreen,
+}
+
+impl From<DisplayMode> for WindowMode {
+    fn from(mode: DisplayMode) -> Self {
+        match mode {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::SizedFullscreen,
+        }
+    }
+}
+
+/// Changes are applied immediately
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    /// Window size; ignored in borderless fullscreen
+    pub resolution: UVec2,
+    pub mode: DisplayMode,
+    pub vsync: bool,
+    /// Horizontal field of view of the world camera, degrees. Vertical one is
+    /// calculated from the window aspect ratio.
+    pub field_of_view: f32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            resolution: UVec2::new(1920, 1080),
+            mode: default(),
+            vsync: true,
+            field_of_view: 80.,
+        }
+    }
+}
+
+impl Validate for GraphicsSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.resolution.x == 0 || self.resolution.y == 0 {
+            return Err("resolution can't be zero".to_string());
+        }
+        if !(1. ..180.).contains(&self.field_of_view) {
+            return Err("field_of_view must be in range [1; 180)".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl GraphicsSettings {
+    pub const NAME: &str = "graphics";
+
+    /// Read settings file before [`App`] is created, so window can be created
+    /// with correct parameters. Default is returned on error.
+    pub fn load() -> Self {
+        file_utils::load_valid_ron_file(&settings_filename(Self::NAME)).unwrap_or_default()
+    }
+
+    /// Primary window with these settings
+    pub fn window(&self) -> Window {
+        let mut window = Window {
+            title: "Hovercrab".to_string(),
+            ..default()
+        };
+        self.apply(&mut window);
+        window
+    }
+
+    fn apply(&self, window: &mut Window) {
+        window
+            .resolution
+            .set(self.resolution.x as f32, self.resolution.y as f32);
+        window.mode = self.mode.into();
+        window.present_mode = match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        };
+    }
+}
+
+pub struct GraphicsSettingsPlugin;
+
+impl Plugin for GraphicsSettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_settings::<GraphicsSettings>(GraphicsSettings::NAME)
+            .add_systems(Update, apply_graphics_settings);
+    }
+}
+
+fn apply_graphics_settings(
+    settings: Res<GraphicsSettings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    // window is created with these settings
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        settings.apply(&mut window);
+    }
+}
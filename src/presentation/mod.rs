@@ -2,13 +2,20 @@
 
 use bevy::prelude::*;
 
+pub mod graphics;
 pub mod objects;
 pub mod player;
+pub mod ui;
 
 pub struct PresentationPlugin;
 
 impl Plugin for PresentationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((player::PlayerPlugin, objects::ObjectsPlugin));
+        app.add_plugins((
+            graphics::GraphicsSettingsPlugin,
+            player::PlayerPlugin,
+            objects::ObjectsPlugin,
+            ui::UiPlugin,
+        ));
     }
 }
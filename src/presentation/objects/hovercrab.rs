@@ -1,16 +1,31 @@
 //! Hovercrab
 
-use crate::{gameplay::objects::hovercrab::Hovercrab, utils::for_crate::bevy::FallibleCommands};
-use bevy::prelude::*;
+use crate::{
+    gameplay::{
+        objects::hovercrab::Hovercrab,
+        physics::{InterpolatedTransform, InterpolationSet},
+    },
+    utils::for_crate::bevy::FallibleCommands,
+};
+use bevy::{prelude::*, transform::TransformSystem};
 
 pub struct HovercrabPlugin;
 
 impl Plugin for HovercrabPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (spawn_hovercrab,));
+        app.add_systems(Update, (spawn_hovercrab,)).add_systems(
+            PostUpdate,
+            interpolate_model
+                .after(InterpolationSet)
+                .before(TransformSystem::TransformPropagate),
+        );
     }
 }
 
+/// Visual part of the hovercrab, child of the physical one
+#[derive(Component)]
+struct HovercrabModel;
+
 fn spawn_hovercrab(
     mut commands: Commands,
     entities: Query<Entity, Added<Hovercrab>>,
@@ -19,7 +34,25 @@ fn spawn_hovercrab(
     for entity in entities.iter() {
         let scene = asset_server.load("models/hovercrab.glb#Scene0");
         commands.try_with_children(entity, |parent| {
-            parent.spawn(SceneBundle { scene, ..default() });
+            parent.spawn((SceneBundle { scene, ..default() }, HovercrabModel));
         });
     }
 }
+
+/// Offset model, so it's rendered at interpolated position instead of the
+/// physical one
+fn interpolate_model(
+    crabs: Query<(&Transform, &InterpolatedTransform, &Children), With<Hovercrab>>,
+    mut models: Query<&mut Transform, (With<HovercrabModel>, Without<Hovercrab>)>,
+) {
+    for (transform, interpolated, children) in crabs.iter() {
+        let offset = transform.compute_affine().inverse() * interpolated.value.compute_affine();
+        let offset = Transform::from_matrix(offset.into());
+
+        for child in children.iter() {
+            if let Ok(mut model) = models.get_mut(*child) {
+                *model = offset;
+            }
+        }
+    }
+}
@@ -1,15 +1,173 @@
+//! Input bindings
+
+use crate::utils::{file_utils::Validate, plugins::settings::SettingsApp};
 use bevy::{app::AppExit, prelude::*};
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Actions for controlling the hovercrab, see
+/// [`super::local_player::LocalPlayer`]
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
+pub enum HovercrabAction {
+    /// Dual-axis, relative to the camera
+    Move,
+    Ascend,
+    Descend,
+    Boost,
+    Brake,
+    Hop,
+    /// Cycle [`super::local_player::SteeringScheme`]
+    NextSteering,
+}
+
+/// Actions for controlling the camera
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
+pub enum CameraAction {
+    /// Dual-axis, for gamepad sticks. Deadzone and response curve are in
+    /// [`super::mouselook::MouselookSettings`].
+    Look,
+    /// Single-axis, changes distance of [`super::mouselook::OrbitCamera`].
+    /// Mouse wheel always zooms and shouldn't be bound to it.
+    Zoom,
+    /// Hold to look back without changing direction of movement
+    LookBehind,
+    /// Hold to rotate camera without changing direction of movement
+    FreeLook,
+    /// Cycle [`super::camera_modes::CameraMode`]
+    NextMode,
+    /// Dual-axis, free-fly camera movement
+    FlyMove,
+    FlyUp,
+    FlyDown,
+    FlyFast,
+}
+
+/// Actions not related to the gameplay
+#[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect, Serialize, Deserialize)]
+pub enum UiAction {
+    Exit,
+    /// Debug: respawn the player
+    Respawn,
+    /// Show or hide window with log messages
+    LogConsole,
+    /// Show or hide developer console
+    DevConsole,
+    /// Show or hide hovercrab tuning window
+    HovercrabInspector,
+}
+
+/// All input bindings. Loaded from and saved to the settings file.
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputBindings {
+    pub hovercrab: InputMap<HovercrabAction>,
+    pub camera: InputMap<CameraAction>,
+    pub ui: InputMap<UiAction>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut hovercrab = InputMap::default();
+        hovercrab
+            .insert(VirtualDPad::wasd(), HovercrabAction::Move)
+            .insert(DualAxis::left_stick(), HovercrabAction::Move)
+            .insert(KeyCode::Z, HovercrabAction::Ascend)
+            .insert(GamepadButtonType::DPadUp, HovercrabAction::Ascend)
+            .insert(KeyCode::X, HovercrabAction::Descend)
+            .insert(GamepadButtonType::DPadDown, HovercrabAction::Descend)
+            .insert(KeyCode::ShiftLeft, HovercrabAction::Boost)
+            .insert(GamepadButtonType::RightTrigger2, HovercrabAction::Boost)
+            .insert(KeyCode::ControlLeft, HovercrabAction::Brake)
+            .insert(GamepadButtonType::LeftTrigger2, HovercrabAction::Brake)
+            .insert(KeyCode::Space, HovercrabAction::Hop)
+            .insert(MouseButton::Right, HovercrabAction::Hop)
+            .insert(GamepadButtonType::South, HovercrabAction::Hop)
+            .insert(KeyCode::V, HovercrabAction::NextSteering)
+            .insert(GamepadButtonType::West, HovercrabAction::NextSteering);
+
+        let mut camera = InputMap::default();
+        camera
+            .insert(
+                DualAxis::symmetric(
+                    GamepadAxisType::RightStickX,
+                    GamepadAxisType::RightStickY,
+                    0.,
+                ),
+                CameraAction::Look,
+            )
+            .insert(KeyCode::B, CameraAction::LookBehind)
+            .insert(MouseButton::Middle, CameraAction::LookBehind)
+            .insert(GamepadButtonType::RightThumb, CameraAction::LookBehind)
+            .insert(KeyCode::AltLeft, CameraAction::FreeLook)
+            .insert(GamepadButtonType::LeftThumb, CameraAction::FreeLook)
+            .insert(KeyCode::C, CameraAction::NextMode)
+            .insert(GamepadButtonType::North, CameraAction::NextMode)
+            .insert(VirtualDPad::wasd(), CameraAction::FlyMove)
+            .insert(DualAxis::left_stick(), CameraAction::FlyMove)
+            .insert(KeyCode::E, CameraAction::FlyUp)
+            .insert(GamepadButtonType::RightTrigger2, CameraAction::FlyUp)
+            .insert(KeyCode::Q, CameraAction::FlyDown)
+            .insert(GamepadButtonType::LeftTrigger2, CameraAction::FlyDown)
+            .insert(KeyCode::ShiftLeft, CameraAction::FlyFast)
+            .insert(GamepadButtonType::South, CameraAction::FlyFast);
+
+        let mut ui = InputMap::default();
+        ui.insert(
+            UserInput::chord([KeyCode::ControlLeft, KeyCode::Q]),
+            UiAction::Exit,
+        )
+        .insert(KeyCode::R, UiAction::Respawn)
+        .insert(GamepadButtonType::Select, UiAction::Respawn)
+        .insert(KeyCode::F1, UiAction::LogConsole)
+        .insert(KeyCode::Grave, UiAction::DevConsole)
+        .insert(KeyCode::F2, UiAction::HovercrabInspector);
+
+        Self {
+            hovercrab,
+            camera,
+            ui,
+        }
+    }
+}
+
+impl Validate for InputBindings {}
 
 pub struct ActionsPlugin;
 
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreUpdate, (exit_on_ctrl_q,));
+        app.add_plugins((
+            InputManagerPlugin::<HovercrabAction>::default(),
+            InputManagerPlugin::<CameraAction>::default(),
+            InputManagerPlugin::<UiAction>::default(),
+        ))
+        .add_settings::<InputBindings>("input")
+        .init_resource::<ActionState<HovercrabAction>>()
+        .init_resource::<ActionState<CameraAction>>()
+        .init_resource::<ActionState<UiAction>>()
+        .init_resource::<InputMap<HovercrabAction>>()
+        .init_resource::<InputMap<CameraAction>>()
+        .init_resource::<InputMap<UiAction>>()
+        .add_systems(PreUpdate, apply_input_bindings)
+        .add_systems(Update, exit_on_action);
+    }
+}
+
+fn apply_input_bindings(
+    bindings: Res<InputBindings>,
+    mut hovercrab: ResMut<InputMap<HovercrabAction>>,
+    mut camera: ResMut<InputMap<CameraAction>>,
+    mut ui: ResMut<InputMap<UiAction>>,
+) {
+    if bindings.is_changed() {
+        *hovercrab = bindings.hovercrab.clone();
+        *camera = bindings.camera.clone();
+        *ui = bindings.ui.clone();
     }
 }
 
-fn exit_on_ctrl_q(keys: Res<Input<KeyCode>>, mut exit: EventWriter<AppExit>) {
-    if keys.just_pressed(KeyCode::Q) && keys.pressed(KeyCode::ControlLeft) {
+fn exit_on_action(actions: Res<ActionState<UiAction>>, mut exit: EventWriter<AppExit>) {
+    if actions.just_pressed(UiAction::Exit) {
         exit.send_default()
     }
 }
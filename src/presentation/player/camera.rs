@@ -1,20 +1,35 @@
-use crate::utils::{for_crate::bevy::FallibleCommands, math_algorithms};
+use crate::{
+    presentation::graphics::GraphicsSettings,
+    utils::{for_crate::bevy::FallibleCommands, math_algorithms},
+};
 use bevy::{
     core_pipeline::{bloom::BloomSettings, fxaa::Fxaa, tonemapping::Tonemapping, Skybox},
     prelude::*,
-    render::render_resource::{TextureViewDescriptor, TextureViewDimension},
+    render::{
+        camera::CameraUpdateSystem,
+        render_resource::{TextureViewDescriptor, TextureViewDimension},
+    },
+    window::PrimaryWindow,
 };
 
 #[derive(Component)]
 pub struct WorldCamera;
 
+/// Added to horizontal field of view of [`WorldCamera`] (i.e. for speed
+/// effects). Base value is [`GraphicsSettings::field_of_view`].
+#[derive(Component, Default)]
+pub struct ExtraFov {
+    pub degrees: f32,
+}
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<SkyboxResource>()
             .add_systems(Startup, load_skybox)
-            .add_systems(Update, (convert_skybox, set_skybox, spawn_world_camera));
+            .add_systems(Update, (convert_skybox, set_skybox, spawn_world_camera))
+            .add_systems(PostUpdate, update_fov.before(CameraUpdateSystem));
     }
 }
 
@@ -64,9 +79,7 @@ fn set_skybox(
 }
 
 fn spawn_world_camera(cameras: Query<Entity, Added<WorldCamera>>, mut commands: Commands) {
-    let field_of_view = 80_f32; // degrees; TODO: read from settings
-    let aspect_ratio = 1920. / 1080.; // TODO: use real one
-
+    // field of view is set in update_fov
     for entity in cameras.iter() {
         commands.try_insert(
             entity,
@@ -76,21 +89,46 @@ fn spawn_world_camera(cameras: Query<Entity, Added<WorldCamera>>, mut commands:
                         hdr: true,
                         ..default()
                     },
-                    projection: PerspectiveProjection {
-                        fov: math_algorithms::calculate_vertical_fov(
-                            field_of_view.to_radians(),
-                            aspect_ratio,
-                        ),
-                        ..default()
-                    }
-                    .into(),
                     tonemapping: Tonemapping::TonyMcMapface,
                     ..default()
                 },
                 BloomSettings::NATURAL,
                 Skybox(default()),
                 Fxaa::default(),
+                ExtraFov::default(),
             ),
         );
     }
 }
+
+/// Calculate vertical field of view from the horizontal one and actual aspect
+/// ratio of the window
+fn update_fov(
+    mut cameras: Query<(&mut Projection, &ExtraFov)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<GraphicsSettings>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return
+    };
+    if window.width() <= 0. || window.height() <= 0. {
+        return; // minimized
+    }
+    let aspect_ratio = window.width() / window.height();
+
+    for (mut projection, extra_fov) in cameras.iter_mut() {
+        let fov = math_algorithms::calculate_vertical_fov(
+            (settings.field_of_view + extra_fov.degrees).to_radians(),
+            aspect_ratio,
+        );
+
+        // don't trigger change detection each frame
+        if let Projection::Perspective(perspective) = &*projection {
+            if perspective.fov != fov {
+                if let Projection::Perspective(perspective) = projection.as_mut() {
+                    perspective.fov = fov;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! Switching between different camera behaviours
+
+use super::{
+    actions::CameraAction,
+    camera::ExtraFov,
+    mouselook::{InputControl, OrbitCamera},
+};
+use crate::{
+    gameplay::physics::{InterpolatedTransform, InterpolationSet},
+    utils::{
+        file_utils::{self, Validate},
+        math_algorithms,
+        plugins::settings::SettingsApp,
+    },
+};
+use bevy::{prelude::*, render::view::VisibilitySystems, transform::TransformSystem};
+use bevy_rapier3d::prelude::Velocity;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CameraMode {
+    /// Rotated by mouse around the target, see [`OrbitCamera`]
+    #[default]
+    Orbit,
+    /// Behind the target, looking in direction of its movement
+    Chase,
+    /// Attached to the target, rotated by mouse
+    Cockpit,
+    /// Nearest of [`TracksideCamera`] points, looking at the target
+    Trackside,
+    /// Debug: moves freely, ignoring the target. Hovercrab isn't controlled.
+    FreeFly,
+}
+
+impl CameraMode {
+    /// Next mode in cycle
+    pub fn next(self) -> Self {
+        match self {
+            Self::Orbit => Self::Chase,
+            Self::Chase => Self::Cockpit,
+            Self::Cockpit => Self::Trackside,
+            Self::Trackside => Self::FreeFly,
+            Self::FreeFly => Self::Orbit,
+        }
+    }
+
+    /// Whether rotation is set by [`super::mouselook::MouselookController`]
+    pub fn uses_mouselook(self) -> bool {
+        matches!(self, Self::Orbit | Self::Cockpit | Self::FreeFly)
+    }
+}
+
+/// Camera which can be switched between [`CameraMode`]s with
+/// [`CameraAction::NextMode`]. Switch is smoothed.
+///
+/// [`OrbitCamera`] must be present for orbit mode to work.
+#[derive(Component, Default)]
+pub struct CameraModes {
+    pub mode: CameraMode,
+    pub target: Option<Entity>,
+
+    transition: Option<Transition>,
+    chase_direction: Option<Vec3>,
+    chase_velocity: Vec3,
+}
+
+impl CameraModes {
+    pub fn new(mode: CameraMode, target: Option<Entity>) -> Self {
+        Self {
+            mode,
+            target,
+            ..default()
+        }
+    }
+}
+
+struct Transition {
+    from: Transform,
+    elapsed: f32,
+}
+
+/// Fixed camera position for [`CameraMode::Trackside`]
+#[derive(Component)]
+pub struct TracksideCamera;
+
+/// Settings used by all entities with [`CameraModes`]
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraModeSettings {
+    /// Duration of the switch between modes, seconds
+    pub transition_time: f32,
+
+    pub chase_distance: f32,
+    pub chase_height: f32,
+    /// How fast chase camera turns after direction of the movement
+    pub chase_stiffness: f32,
+    /// Below that speed chase camera looks where target faces
+    pub chase_min_speed: f32,
+
+    /// Position of the cockpit camera relative to the target
+    pub cockpit_offset: Vec3,
+
+    /// Free-fly speed, units per second
+    pub fly_speed: f32,
+    /// Speed multiplier when [`CameraAction::FlyFast`] is pressed
+    pub fly_fast_multiplier: f32,
+}
+
+impl Default for CameraModeSettings {
+    fn default() -> Self {
+        Self {
+            transition_time: 0.4,
+            chase_distance: 10.,
+            chase_height: 3.,
+            chase_stiffness: 4.,
+            chase_min_speed: 2.,
+            cockpit_offset: Vec3::new(0., 1., -0.5),
+            fly_speed: 15.,
+            fly_fast_multiplier: 4.,
+        }
+    }
+}
+
+impl Validate for CameraModeSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !self.cockpit_offset.is_finite() {
+            return Err("cockpit_offset must be finite".to_string());
+        }
+        file_utils::check_finite([
+            ("transition_time", self.transition_time),
+            ("chase_distance", self.chase_distance),
+            ("chase_height", self.chase_height),
+            ("chase_stiffness", self.chase_stiffness),
+            ("chase_min_speed", self.chase_min_speed),
+            ("fly_speed", self.fly_speed),
+            ("fly_fast_multiplier", self.fly_fast_multiplier),
+        ])?;
+        if self.transition_time < 0. {
+            return Err("transition_time can't be negative".to_string());
+        }
+        if self.chase_stiffness <= 0. {
+            return Err("chase_stiffness must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Camera modes are updated in [`PostUpdate`], after physics interpolation
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct CameraModeSet;
+
+pub struct CameraModesPlugin;
+
+impl Plugin for CameraModesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_settings::<CameraModeSettings>("camera_modes")
+            .configure_set(
+                PostUpdate,
+                CameraModeSet
+                    .after(InterpolationSet)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(Update, switch_camera_mode)
+            .add_systems(
+                PostUpdate,
+                (
+                    chase_camera,
+                    cockpit_camera,
+                    trackside_camera,
+                    free_fly_camera,
+                )
+                    .in_set(CameraModeSet),
+            )
+            .add_systems(
+                PostUpdate,
+                camera_transition
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::UpdatePerspectiveFrusta),
+            );
+    }
+}
+
+fn switch_camera_mode(
+    mut cameras: Query<(
+        &mut CameraModes,
+        &GlobalTransform,
+        Option<&mut OrbitCamera>,
+        Option<&mut ExtraFov>,
+    )>,
+    actions: Res<ActionState<CameraAction>>,
+    controls: Res<InputControl>,
+) {
+    if !controls.mouselook_enabled || !actions.just_pressed(CameraAction::NextMode) {
+        return;
+    }
+
+    for (mut modes, global, orbit, extra_fov) in cameras.iter_mut() {
+        modes.mode = modes.mode.next();
+        modes.transition = Some(Transition {
+            // what is currently rendered, including unfinished transition
+            from: global.compute_transform(),
+            elapsed: 0.,
+        });
+        modes.chase_direction = None;
+
+        if let Some(mut orbit) = orbit {
+            orbit.reset_follow();
+        }
+        if let Some(mut extra_fov) = extra_fov {
+            extra_fov.degrees = 0.;
+        }
+
+        info!("camera mode: {:?}", modes.mode);
+    }
+}
+
+type TargetQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static GlobalTransform,
+        Option<&'static InterpolatedTransform>,
+        Option<&'static Velocity>,
+    ),
+>;
+
+/// Transform and velocity of the target
+fn target_state(target: Option<Entity>, targets: &TargetQuery) -> Option<(Transform, Vec3)> {
+    let (global, interpolated, velocity) = targets.get(target?).ok()?;
+    let transform = interpolated
+        .map(|t| t.value)
+        .unwrap_or_else(|| global.compute_transform());
+    let velocity = velocity.map(|v| v.linvel).unwrap_or_default();
+    Some((transform, velocity))
+}
+
+fn chase_camera(
+    mut cameras: Query<(&mut Transform, &mut CameraModes)>,
+    targets: TargetQuery,
+    settings: Res<CameraModeSettings>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut modes) in cameras.iter_mut() {
+        if modes.mode != CameraMode::Chase {
+            continue;
+        }
+        let Some((target, velocity)) = target_state(modes.target, &targets) else {
+            continue
+        };
+
+        let horizontal_velocity = Vec3::new(velocity.x, 0., velocity.z);
+        let direction = if horizontal_velocity.length() > settings.chase_min_speed {
+            horizontal_velocity
+        } else {
+            let forward = target.rotation * Vec3::NEG_Z;
+            Vec3::new(forward.x, 0., forward.z)
+        }
+        .try_normalize()
+        .unwrap_or(Vec3::NEG_Z);
+
+        let modes = &mut *modes;
+        let direction = match modes.chase_direction {
+            Some(current) => math_algorithms::smooth_damp(
+                current,
+                direction,
+                &mut modes.chase_velocity,
+                settings.chase_stiffness,
+                time.delta_seconds(),
+            )
+            .try_normalize()
+            .unwrap_or(direction),
+            None => direction,
+        };
+        modes.chase_direction = Some(direction);
+
+        let position = target.translation - direction * settings.chase_distance
+            + Vec3::Y * settings.chase_height;
+        *transform = Transform::from_translation(position).looking_at(target.translation, Vec3::Y);
+    }
+}
+
+fn cockpit_camera(
+    mut cameras: Query<(&mut Transform, &CameraModes)>,
+    targets: TargetQuery,
+    settings: Res<CameraModeSettings>,
+) {
+    for (mut transform, modes) in cameras.iter_mut() {
+        if modes.mode != CameraMode::Cockpit {
+            continue;
+        }
+        if let Some((target, _)) = target_state(modes.target, &targets) {
+            transform.translation = target.transform_point(settings.cockpit_offset);
+        }
+    }
+}
+
+fn trackside_camera(
+    mut cameras: Query<(&mut Transform, &CameraModes), Without<TracksideCamera>>,
+    targets: TargetQuery,
+    points: Query<&GlobalTransform, With<TracksideCamera>>,
+) {
+    for (mut transform, modes) in cameras.iter_mut() {
+        if modes.mode != CameraMode::Trackside {
+            continue;
+        }
+        let Some((target, _)) = target_state(modes.target, &targets) else {
+            continue
+        };
+
+        // if there are no points, camera stays where it is
+        let position = points
+            .iter()
+            .map(|point| point.translation())
+            .min_by(|a, b| {
+                a.distance_squared(target.translation)
+                    .total_cmp(&b.distance_squared(target.translation))
+            })
+            .unwrap_or(transform.translation);
+
+        if position.distance_squared(target.translation) > 1e-5 {
+            *transform =
+                Transform::from_translation(position).looking_at(target.translation, Vec3::Y);
+        }
+    }
+}
+
+fn free_fly_camera(
+    mut cameras: Query<(&mut Transform, &CameraModes)>,
+    actions: Res<ActionState<CameraAction>>,
+    controls: Res<InputControl>,
+    settings: Res<CameraModeSettings>,
+    time: Res<Time>,
+) {
+    if !controls.mouselook_enabled {
+        return;
+    }
+
+    let mov = actions
+        .axis_pair(CameraAction::FlyMove)
+        .map(|axis| axis.xy())
+        .unwrap_or_default()
+        .clamp_length_max(1.);
+    let mut mov = Vec3::new(mov.x, 0., -mov.y);
+    if actions.pressed(CameraAction::FlyUp) {
+        mov.y += 1.
+    }
+    if actions.pressed(CameraAction::FlyDown) {
+        mov.y -= 1.
+    }
+
+    let mut speed = settings.fly_speed;
+    if actions.pressed(CameraAction::FlyFast) {
+        speed *= settings.fly_fast_multiplier;
+    }
+
+    for (mut transform, modes) in cameras.iter_mut() {
+        if modes.mode != CameraMode::FreeFly {
+            continue;
+        }
+        let rotation = transform.rotation;
+        transform.translation += rotation * mov * speed * time.delta_seconds();
+    }
+}
+
+/// Blends rendered transform from the one before mode switch. [`Transform`]
+/// itself isn't changed, so modes don't see the blending.
+fn camera_transition(
+    mut cameras: Query<(&mut GlobalTransform, &Transform, &mut CameraModes)>,
+    settings: Res<CameraModeSettings>,
+    time: Res<Time>,
+) {
+    for (mut global, transform, mut modes) in cameras.iter_mut() {
+        let Some(transition) = modes.transition.as_mut() else {
+            continue
+        };
+
+        transition.elapsed += time.delta_seconds();
+        if transition.elapsed >= settings.transition_time {
+            // transform propagation skips unchanged transforms
+            *global = (*transform).into();
+            modes.transition = None;
+            continue;
+        }
+
+        let t = transition.elapsed / settings.transition_time;
+        let t = t * t * (3. - 2. * t); // smoothstep
+
+        let from = transition.from;
+        *global = Transform {
+            translation: from.translation.lerp(transform.translation, t),
+            rotation: from.rotation.slerp(transform.rotation, t),
+            scale: transform.scale,
+        }
+        .into();
+    }
+}
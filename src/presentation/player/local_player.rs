@@ -0,0 +1,183 @@
+//! Control of the hovercrab by the local player
+
+use crate::{
+    gameplay::{
+        objects::hovercrab::{HovercrabControls, HovercrabControlsSet},
+        physics::InterpolatedTransform,
+    },
+    presentation::player::{
+        actions::HovercrabAction,
+        camera_modes::{CameraMode, CameraModes},
+        mouselook::{ControlRotation, OrbitCamera},
+    },
+    utils::{
+        file_utils::Validate,
+        math_algorithms,
+        plugins::{
+            console::{parse_console_args, ConsoleApp},
+            settings::SettingsApp,
+        },
+    },
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+use leafwing_input_manager::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+/// Hovercrab controlled by the local player with [`HovercrabAction`].
+///
+/// Steering depends on [`SteeringSettings`]. Not controlled while camera is in
+/// [`CameraMode::FreeFly`].
+#[derive(Component)]
+pub struct LocalPlayer {
+    pub camera: Entity,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum SteeringScheme {
+    /// Movement is relative to the camera (its [`ControlRotation`], if
+    /// present), hovercrab turns where camera looks
+    #[default]
+    CameraRelative,
+    /// Forward and backward movement only; left and right turn the hovercrab
+    Tank,
+    /// Movement is relative to the camera, but hovercrab doesn't turn
+    CrabWalk,
+}
+
+impl SteeringScheme {
+    /// Next scheme in cycle
+    pub fn next(self) -> Self {
+        match self {
+            Self::CameraRelative => Self::Tank,
+            Self::Tank => Self::CrabWalk,
+            Self::CrabWalk => Self::CameraRelative,
+        }
+    }
+}
+
+#[derive(Resource, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SteeringSettings {
+    /// Changed by [`HovercrabAction::NextSteering`]
+    pub scheme: SteeringScheme,
+}
+
+impl Validate for SteeringSettings {}
+
+pub struct LocalPlayerPlugin;
+
+impl Plugin for LocalPlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_settings::<SteeringSettings>("steering")
+            .add_systems(Update, switch_steering)
+            .add_systems(
+                FixedUpdate,
+                local_player_controls.in_set(HovercrabControlsSet),
+            )
+            .add_console_command(
+                "teleport",
+                "<x> <y> <z>",
+                "move player's hovercrab to the position and stop it",
+                |world, args| {
+                    let position = Vec3::from_array(parse_console_args(args)?);
+
+                    let mut players = world.query_filtered::<(
+                        &mut Transform,
+                        Option<&mut Velocity>,
+                        Option<&mut InterpolatedTransform>,
+                    ), With<LocalPlayer>>();
+                    let mut count = 0;
+                    for (mut transform, velocity, interpolated) in players.iter_mut(world) {
+                        transform.translation = position;
+                        if let Some(mut velocity) = velocity {
+                            *velocity = default();
+                        }
+                        if let Some(mut interpolated) = interpolated {
+                            interpolated.reset();
+                        }
+                        count += 1;
+                    }
+                    if count == 0 {
+                        return Err("No player".to_string());
+                    }
+
+                    // don't fly camera across the level
+                    for mut camera in world.query::<&mut OrbitCamera>().iter_mut(world) {
+                        camera.reset_follow();
+                    }
+                    Ok(format!("Teleported to {position}"))
+                },
+            );
+    }
+}
+
+fn switch_steering(
+    mut settings: ResMut<SteeringSettings>,
+    actions: Res<ActionState<HovercrabAction>>,
+) {
+    if actions.just_pressed(HovercrabAction::NextSteering) {
+        settings.scheme = settings.scheme.next();
+        info!("steering: {:?}", settings.scheme);
+    }
+}
+
+fn local_player_controls(
+    mut players: Query<(&LocalPlayer, &mut HovercrabControls, &Transform)>,
+    cameras: Query<(&Transform, Option<&ControlRotation>, Option<&CameraModes>)>,
+    actions: Res<ActionState<HovercrabAction>>,
+    settings: Res<SteeringSettings>,
+) {
+    for (player, mut controls, transform) in players.iter_mut() {
+        let (rotation, modes) = cameras
+            .get(player.camera)
+            .map(|(t, control, modes)| (control.map_or(t.rotation, |c| c.0), modes))
+            .unwrap_or_default();
+
+        if modes.is_some_and(|modes| modes.mode == CameraMode::FreeFly) {
+            *controls = default();
+            continue;
+        }
+
+        let mov = actions
+            .axis_pair(HovercrabAction::Move)
+            .map(|axis| axis.xy())
+            .unwrap_or_default()
+            .clamp_length_max(1.);
+        let mut mov = Vec3::new(mov.x, 0., -mov.y);
+
+        if actions.pressed(HovercrabAction::Ascend) {
+            mov.y += 1.
+        }
+        if actions.pressed(HovercrabAction::Descend) {
+            mov.y -= 1.
+        }
+
+        let heading = math_algorithms::quat_component(transform.rotation, Vec3::Y);
+        let camera_heading = math_algorithms::quat_component(rotation, Vec3::Y);
+
+        match settings.scheme {
+            SteeringScheme::CameraRelative => {
+                controls.movement = mov;
+                controls.target_rotation = rotation * Vec3::NEG_Z;
+            }
+            SteeringScheme::Tank => {
+                controls.movement = Vec3::new(0., mov.y, mov.z);
+                controls.target_rotation =
+                    heading * Quat::from_rotation_y(-mov.x * FRAC_PI_2) * Vec3::NEG_Z;
+            }
+            SteeringScheme::CrabWalk => {
+                // from camera-relative to hovercrab-relative
+                controls.movement = heading.inverse() * camera_heading * mov;
+                // hold current heading
+                controls.target_rotation = heading * Vec3::NEG_Z;
+            }
+        }
+
+        // analog for gamepad triggers
+        controls.boost = actions.value(HovercrabAction::Boost).clamp(0., 1.);
+        controls.brake = actions.pressed(HovercrabAction::Brake);
+        controls.hop = actions.pressed(HovercrabAction::Hop);
+    }
+}
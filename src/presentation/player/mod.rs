@@ -2,6 +2,8 @@ use bevy::prelude::*;
 
 pub mod actions;
 pub mod camera;
+pub mod camera_modes;
+pub mod local_player;
 pub mod mouselook;
 
 pub struct PlayerPlugin;
@@ -10,8 +12,10 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             camera::CameraPlugin,
+            camera_modes::CameraModesPlugin,
             mouselook::MouselookPlugin,
             actions::ActionsPlugin,
+            local_player::LocalPlayerPlugin,
         ));
     }
 }
@@ -1,12 +1,28 @@
 use bevy::{
-    input::mouse::MouseMotion,
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     prelude::*,
     window::{CursorGrabMode, PrimaryWindow},
 };
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
-use crate::utils::math_algorithms;
+use super::{
+    actions::CameraAction,
+    camera::ExtraFov,
+    camera_modes::{CameraMode, CameraModes},
+};
+use crate::{
+    gameplay::physics::{InterpolatedTransform, InterpolationSet},
+    utils::{
+        file_utils::{self, Validate},
+        for_crate::bevy::FallibleCommands,
+        math_algorithms,
+        plugins::settings::SettingsApp,
+    },
+};
+use bevy::transform::TransformSystem;
+use bevy_rapier3d::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*};
 
 /// Apply mouse movement in primary window to [`Transform::rotation`] of this
 /// entity.
@@ -16,12 +32,22 @@ use crate::utils::math_algorithms;
 /// When active (even if no
 /// [`MouselookController`] component exists), cursor is hidden and confined to
 /// the window.
+///
+/// If [`CameraModes`] is present, works only in modes which use mouselook.
 #[derive(Component)]
 pub struct MouselookController {
     /// If true, camera isn't locked at min/max elevation.
     pub allow_flip: bool,
 }
 
+/// Rotation used for steering, added automatically to entities with
+/// [`MouselookController`].
+///
+/// Same as [`Transform::rotation`], except while free-look or look-behind is
+/// used ([`CameraAction::FreeLook`], [`CameraAction::LookBehind`]).
+#[derive(Component)]
+pub struct ControlRotation(pub Quat);
+
 /// Settings used by all entities with [`MouselookController`].
 #[derive(Resource, Serialize, Deserialize)]
 #[serde(default)]
@@ -29,25 +55,213 @@ pub struct MouselookSettings {
     // By how much rotate camera if cursor moved horizontally from one edge of the screen to the
     // other.
     pub sensitivity_degrees: f32,
+    /// Same as `sensitivity_degrees`, but for vertical movement (distance is
+    /// still relative to the screen width)
+    pub vertical_sensitivity_degrees: f32,
+    /// Invert vertical movement, for both mouse and gamepad
+    pub invert_y: bool,
+    /// Time over which mouse movement is smoothed, seconds. Zero disables
+    /// smoothing.
+    pub smoothing: f32,
+    /// Use mouse movement as is. If false, fast movements rotate camera more
+    /// (see `acceleration`).
+    pub raw_input: bool,
+    /// If `raw_input` is false, movement is multiplied by 1 + `acceleration` *
+    /// speed, where speed is in screen widths per second
+    pub acceleration: f32,
+
+    /// Rotation speed with gamepad stick fully deflected, degrees per second
+    pub gamepad_sensitivity_degrees: f32,
+    /// Stick positions closer than that to the center are ignored, from 0 to 1
+    pub gamepad_deadzone: f32,
+    /// Response curve of the stick: 1 is linear, greater values give more
+    /// precision near the center
+    pub gamepad_response_exponent: f32,
+
+    /// How fast camera returns to [`ControlRotation`] after free-look is
+    /// released
+    pub free_look_return_stiffness: f32,
 }
 
 impl Default for MouselookSettings {
     fn default() -> Self {
         Self {
             sensitivity_degrees: 200.,
+            vertical_sensitivity_degrees: 200.,
+            invert_y: false,
+            smoothing: 0.,
+            raw_input: true,
+            acceleration: 1.,
+            gamepad_sensitivity_degrees: 180.,
+            gamepad_deadzone: 0.15,
+            gamepad_response_exponent: 2.,
+            free_look_return_stiffness: 8.,
+        }
+    }
+}
+
+impl Validate for MouselookSettings {
+    fn validate(&self) -> Result<(), String> {
+        file_utils::check_finite([
+            ("sensitivity_degrees", self.sensitivity_degrees),
+            (
+                "vertical_sensitivity_degrees",
+                self.vertical_sensitivity_degrees,
+            ),
+            ("smoothing", self.smoothing),
+            ("acceleration", self.acceleration),
+            (
+                "gamepad_sensitivity_degrees",
+                self.gamepad_sensitivity_degrees,
+            ),
+            ("gamepad_deadzone", self.gamepad_deadzone),
+            ("gamepad_response_exponent", self.gamepad_response_exponent),
+            (
+                "free_look_return_stiffness",
+                self.free_look_return_stiffness,
+            ),
+        ])?;
+        if self.smoothing < 0. {
+            return Err("smoothing can't be negative".to_string());
         }
+        Ok(())
     }
 }
 
-/// If target is set, follows it.
+/// If target is set, follows it (with a delay, see [`OrbitCameraSettings`]).
 ///
-/// Sets XZ-translation based on rotation.
+/// Sets XZ-translation based on rotation. If [`CameraModes`] is present, works
+/// only in [`CameraMode::Orbit`].
 #[derive(Component)]
 pub struct OrbitCamera {
     pub target: Option<Entity>,
-    pub distance: f32,
     /// relative to horizontal rotation
     pub offset: Vec3,
+
+    follow: FollowState,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Option<Entity>, offset: Vec3) -> Self {
+        Self {
+            target,
+            offset,
+            follow: default(),
+        }
+    }
+
+    /// Move to the target immediately, without smoothing
+    pub fn reset_follow(&mut self) {
+        self.follow = default();
+    }
+}
+
+#[derive(Default)]
+struct FollowState {
+    /// Smoothed target position; none if camera doesn't follow anything yet
+    position: Option<Vec3>,
+    horizontal_velocity: Vec2,
+    vertical_velocity: f32,
+
+    /// Smoothed speed of the target
+    speed: f32,
+    speed_velocity: f32,
+
+    /// Current distance, possibly reduced to avoid collisions
+    distance: Option<f32>,
+    distance_velocity: f32,
+}
+
+/// Settings used by all entities with [`OrbitCamera`]
+#[derive(Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrbitCameraSettings {
+    /// Distance to the target, changed by [`CameraAction::Zoom`]
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Relative change of the distance per one step of mouse wheel
+    pub zoom_step: f32,
+
+    /// How fast camera catches up with the target horizontally; higher is
+    /// faster
+    pub translation_stiffness: f32,
+    /// Same as `translation_stiffness`, but for vertical movement
+    pub height_stiffness: f32,
+
+    /// How fast speed effects react to changes in target speed
+    pub speed_stiffness: f32,
+    /// Additional distance to the target per unit of its speed
+    pub speed_distance: f32,
+    pub max_speed_distance: f32,
+    /// Additional horizontal field of view per unit of target speed
+    pub speed_fov_degrees: f32,
+    pub max_speed_fov_degrees: f32,
+
+    /// Radius of the sphere which is kept from intersecting level geometry
+    pub collision_radius: f32,
+    /// How fast camera moves back after obstacle is gone. Camera is pulled in
+    /// instantly.
+    pub collision_recovery_stiffness: f32,
+}
+
+impl Default for OrbitCameraSettings {
+    fn default() -> Self {
+        Self {
+            distance: 12.,
+            min_distance: 4.,
+            max_distance: 30.,
+            zoom_step: 0.1,
+            translation_stiffness: 12.,
+            height_stiffness: 5.,
+            speed_stiffness: 3.,
+            speed_distance: 0.1,
+            max_speed_distance: 5.,
+            speed_fov_degrees: 0.4,
+            max_speed_fov_degrees: 15.,
+            collision_radius: 0.3,
+            collision_recovery_stiffness: 4.,
+        }
+    }
+}
+
+impl Validate for OrbitCameraSettings {
+    fn validate(&self) -> Result<(), String> {
+        file_utils::check_finite([
+            ("distance", self.distance),
+            ("min_distance", self.min_distance),
+            ("max_distance", self.max_distance),
+            ("zoom_step", self.zoom_step),
+            ("translation_stiffness", self.translation_stiffness),
+            ("height_stiffness", self.height_stiffness),
+            ("speed_stiffness", self.speed_stiffness),
+            ("speed_distance", self.speed_distance),
+            ("max_speed_distance", self.max_speed_distance),
+            ("speed_fov_degrees", self.speed_fov_degrees),
+            ("max_speed_fov_degrees", self.max_speed_fov_degrees),
+            ("collision_radius", self.collision_radius),
+            (
+                "collision_recovery_stiffness",
+                self.collision_recovery_stiffness,
+            ),
+        ])?;
+        if !(0. ..=self.max_distance).contains(&self.min_distance) {
+            return Err("invalid min_distance or max_distance".to_string());
+        }
+        if !(0. ..1.).contains(&self.zoom_step) {
+            return Err("zoom_step must be in range [0; 1)".to_string());
+        }
+        if self.translation_stiffness <= 0. || self.height_stiffness <= 0. {
+            return Err("stiffness must be positive".to_string());
+        }
+        if self.speed_stiffness <= 0. || self.collision_recovery_stiffness <= 0. {
+            return Err("stiffness must be positive".to_string());
+        }
+        if self.collision_radius < 0. {
+            return Err("collision_radius can't be negative".to_string());
+        }
+        Ok(())
+    }
 }
 
 /// Controls controls
@@ -68,12 +282,26 @@ pub struct MouselookPlugin;
 
 impl Plugin for MouselookPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<MouselookSettings>()
+        app.add_settings::<MouselookSettings>("mouselook")
+            .add_settings::<OrbitCameraSettings>("orbit_camera")
             .init_resource::<MouseDelta>()
             .init_resource::<InputControl>()
             // First because mouse events are generated in winit runner
             .add_systems(First, mouse_update)
-            .add_systems(PreUpdate, (mouselook_controller, orbit_camera).chain());
+            .add_systems(
+                PreUpdate,
+                (add_control_rotation, mouselook_controller)
+                    .chain()
+                    .after(InputManagerSystem::Update),
+            )
+            .add_systems(Update, orbit_zoom)
+            // after physics, so target position is up to date
+            .add_systems(
+                PostUpdate,
+                orbit_camera
+                    .after(InterpolationSet)
+                    .before(TransformSystem::TransformPropagate),
+            );
     }
 }
 
@@ -83,6 +311,9 @@ struct MouseDelta {
     ///
     /// Values are relative to window width.
     value: Vec2,
+
+    /// Smoothed speed of the movement, if smoothing is enabled
+    velocity: Vec2,
 }
 
 // grab doesn't work immediatly after window creation
@@ -94,6 +325,7 @@ fn mouse_update(
     mut windows: Query<&mut Window, With<PrimaryWindow>>,
     time: Res<Time>,
     controls: Res<InputControl>,
+    settings: Res<MouselookSettings>,
 ) {
     let Ok(mut window) = windows.get_single_mut() else {
         return
@@ -103,34 +335,169 @@ fn mouse_update(
         controls.mouselook_enabled && window.focused && time.elapsed() > INITIAL_GRAB_DELAY;
 
     if enabled {
-        delta.value = motion
+        let value = motion
             .iter()
             .fold(Vec2::ZERO, |sum, event| sum + event.delta)
             / window.resolution.width();
+        let delta_seconds = time.delta_seconds();
+
+        let value = if settings.raw_input || delta_seconds <= 0. {
+            value
+        } else {
+            let speed = value.length() / delta_seconds;
+            value * (1. + settings.acceleration * speed)
+        };
+
+        delta.value = if settings.smoothing > 0. && delta_seconds > 0. {
+            let t = 1. - (-delta_seconds / settings.smoothing).exp();
+            delta.velocity = math_algorithms::lerp(delta.velocity, value / delta_seconds, t);
+            delta.velocity * delta_seconds
+        } else {
+            value
+        };
 
         window.cursor.visible = false;
         window.cursor.grab_mode = CursorGrabMode::Locked;
     } else {
         delta.value = default();
+        delta.velocity = default();
 
         window.cursor.visible = true;
         window.cursor.grab_mode = CursorGrabMode::None;
     }
 }
 
+fn add_control_rotation(
+    controllers: Query<(Entity, &Transform), Added<MouselookController>>,
+    mut commands: Commands,
+) {
+    for (entity, transform) in controllers.iter() {
+        commands.try_insert(entity, ControlRotation(transform.rotation));
+    }
+}
+
 fn mouselook_controller(
-    mut controllers: Query<(&mut Transform, &MouselookController)>,
+    mut controllers: Query<(
+        &mut Transform,
+        &MouselookController,
+        Option<&mut ControlRotation>,
+        Option<&CameraModes>,
+    )>,
     delta: Res<MouseDelta>,
     settings: Res<MouselookSettings>,
+    actions: Res<ActionState<CameraAction>>,
+    controls: Res<InputControl>,
+    time: Res<Time>,
 ) {
-    let sensitivity = settings.sensitivity_degrees.to_radians();
+    let sensitivity = Vec2::new(
+        settings.sensitivity_degrees.to_radians(),
+        settings.vertical_sensitivity_degrees.to_radians(),
+    );
 
-    for (mut transform, controller) in controllers.iter_mut() {
-        transform.rotation = new_camera_rotation(
-            delta.value * sensitivity,
-            transform.rotation,
-            controller.allow_flip,
-        );
+    let stick = if controls.mouselook_enabled {
+        actions
+            .axis_pair(CameraAction::Look)
+            .map(|axis| axis.xy())
+            .unwrap_or_default()
+    } else {
+        Vec2::ZERO
+    };
+    let stick = math_algorithms::stick_response(
+        stick,
+        settings.gamepad_deadzone,
+        settings.gamepad_response_exponent,
+    );
+    // stick up is positive, but mouse up is negative
+    let stick_angle = Vec2::new(stick.x, -stick.y)
+        * settings.gamepad_sensitivity_degrees.to_radians()
+        * time.delta_seconds();
+
+    let angle = delta.value * sensitivity + stick_angle;
+    let angle = if settings.invert_y {
+        Vec2::new(angle.x, -angle.y)
+    } else {
+        angle
+    };
+
+    let free_look = controls.mouselook_enabled && actions.pressed(CameraAction::FreeLook);
+    let look_behind = controls.mouselook_enabled && actions.pressed(CameraAction::LookBehind);
+    let look_behind_changed = actions.just_pressed(CameraAction::LookBehind)
+        || actions.just_released(CameraAction::LookBehind);
+    let return_t = 1. - (-settings.free_look_return_stiffness * time.delta_seconds()).exp();
+
+    for (mut transform, controller, control, modes) in controllers.iter_mut() {
+        if modes.is_some_and(|modes| !modes.mode.uses_mouselook()) {
+            // rotation is set by the camera mode
+            if let Some(mut control) = control {
+                control.0 = transform.rotation;
+            }
+            continue;
+        }
+
+        let view = new_camera_rotation(angle, transform.rotation, controller.allow_flip);
+
+        let Some(mut control) = control else {
+            transform.rotation = view;
+            continue;
+        };
+
+        if free_look {
+            transform.rotation = view;
+            continue;
+        }
+
+        control.0 = new_camera_rotation(angle, control.0, controller.allow_flip);
+
+        let target = if look_behind {
+            Quat::from_rotation_y(std::f32::consts::PI) * control.0
+        } else {
+            control.0
+        };
+
+        // smooth return from free-look, but look-behind is instant
+        transform.rotation = if look_behind_changed {
+            target
+        } else {
+            view.slerp(target, return_t)
+        };
+    }
+}
+
+/// Touchpads scroll in pixels, mouse wheels in lines
+const SCROLL_PIXELS_PER_LINE: f32 = 50.;
+
+fn orbit_zoom(
+    cameras: Query<Option<&CameraModes>, With<OrbitCamera>>,
+    mut settings: ResMut<OrbitCameraSettings>,
+    actions: Res<ActionState<CameraAction>>,
+    controls: Res<InputControl>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    // read directly, since the action can't convert units
+    let wheel: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / SCROLL_PIXELS_PER_LINE,
+        })
+        .sum();
+
+    let value = actions.value(CameraAction::Zoom) + wheel;
+    if !controls.mouselook_enabled || value == 0. {
+        return;
+    }
+
+    let orbit_active = cameras
+        .iter()
+        .any(|modes| modes.map_or(true, |modes| modes.mode == CameraMode::Orbit));
+    if !orbit_active {
+        return;
+    }
+
+    let distance = (settings.distance * (1. - settings.zoom_step).powf(value))
+        .clamp(settings.min_distance, settings.max_distance);
+    if distance != settings.distance {
+        settings.distance = distance;
     }
 }
 
@@ -162,22 +529,137 @@ fn new_camera_rotation(delta_angle: Vec2, old_rotation: Quat, allow_flip: bool)
 }
 
 fn orbit_camera(
-    mut entities: Query<(&mut Transform, &OrbitCamera)>,
-    targets: Query<&GlobalTransform>,
+    mut entities: Query<(
+        &mut Transform,
+        &mut OrbitCamera,
+        Option<&mut ExtraFov>,
+        Option<&CameraModes>,
+    )>,
+    targets: Query<(
+        &GlobalTransform,
+        Option<&InterpolatedTransform>,
+        Option<&Velocity>,
+    )>,
+    settings: Res<OrbitCameraSettings>,
+    time: Res<Time>,
+    physics: Res<RapierContext>,
 ) {
-    for (mut transform, camera) in entities.iter_mut() {
-        // TODO: interpolation, so camera is a bit delayed
-        let target_pos = camera
+    let _span = info_span!("orbit_camera").entered();
+
+    let delta = time.delta_seconds();
+    let sphere = Collider::ball(settings.collision_radius);
+
+    for (mut transform, mut camera, extra_fov, modes) in entities.iter_mut() {
+        if modes.is_some_and(|modes| modes.mode != CameraMode::Orbit) {
+            continue;
+        }
+
+        let (target_pos, target_speed) = camera
             .target
             .and_then(|e| targets.get(e).ok())
-            .map(|t| t.translation())
+            .map(|(global, interpolated, velocity)| {
+                // interpolated, so camera doesn't jitter if framerate differs from physics rate
+                let position = interpolated
+                    .map(|t| t.value.translation)
+                    .unwrap_or_else(|| global.translation());
+                let speed = velocity.map(|v| v.linvel.length()).unwrap_or_default();
+                (position, speed)
+            })
             .unwrap_or_default();
 
+        let follow = &mut camera.follow;
+
+        let followed = match follow.position {
+            Some(position) => {
+                let horizontal = math_algorithms::smooth_damp(
+                    position.xz(),
+                    target_pos.xz(),
+                    &mut follow.horizontal_velocity,
+                    settings.translation_stiffness,
+                    delta,
+                );
+                let height = math_algorithms::smooth_damp(
+                    position.y,
+                    target_pos.y,
+                    &mut follow.vertical_velocity,
+                    settings.height_stiffness,
+                    delta,
+                );
+                Vec3::new(horizontal.x, height, horizontal.y)
+            }
+            None => target_pos,
+        };
+        follow.position = Some(followed);
+
+        follow.speed = math_algorithms::smooth_damp(
+            follow.speed,
+            target_speed,
+            &mut follow.speed_velocity,
+            settings.speed_stiffness,
+            delta,
+        );
+        let speed = follow.speed.max(0.);
+
+        if let Some(mut extra_fov) = extra_fov {
+            extra_fov.degrees =
+                (speed * settings.speed_fov_degrees).min(settings.max_speed_fov_degrees);
+        }
+
+        let distance = settings
+            .distance
+            .clamp(settings.min_distance, settings.max_distance)
+            + (speed * settings.speed_distance).min(settings.max_speed_distance);
+
         let rotation = transform.rotation;
         let xz_rotation = math_algorithms::quat_component(rotation, Vec3::Y);
 
-        let offset = target_pos + xz_rotation * camera.offset;
+        // target itself is ignored
+        let filter = match camera.target {
+            Some(target) => QueryFilter::default().exclude_rigid_body(target),
+            None => QueryFilter::default(),
+        }
+        .exclude_sensors();
+        let cast = |from: Vec3, to: Vec3| {
+            let path = to - from;
+            let distance = path.length();
+            if distance < 1e-5 {
+                return distance;
+            }
+            physics
+                .cast_shape(
+                    from,
+                    Quat::IDENTITY,
+                    path / distance,
+                    &sphere,
+                    distance,
+                    filter,
+                )
+                .map(|(_, toi)| toi.toi)
+                .unwrap_or(distance)
+        };
+
+        // from target to the center of rotation, then to the camera
+        let offset = followed + xz_rotation * camera.offset;
+        let offset = followed + (offset - followed).normalize_or_zero() * cast(followed, offset);
+        let free_distance = cast(offset, offset + rotation * Vec3::Z * distance);
+
+        let follow = &mut camera.follow;
+        let distance = match follow.distance {
+            Some(current) if current < free_distance => math_algorithms::smooth_damp(
+                current,
+                free_distance,
+                &mut follow.distance_velocity,
+                settings.collision_recovery_stiffness,
+                delta,
+            )
+            .min(free_distance),
+            _ => {
+                follow.distance_velocity = 0.;
+                free_distance
+            }
+        };
+        follow.distance = Some(distance);
 
-        transform.translation = offset + rotation * Vec3::Z * camera.distance;
+        transform.translation = offset + rotation * Vec3::Z * distance;
     }
 }
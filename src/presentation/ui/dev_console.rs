@@ -0,0 +1,227 @@
+//! Developer console window, see [`crate::utils::plugins::console`]
+
+use super::{OpenWindows, WindowToggle};
+use crate::{
+    presentation::player::actions::UiAction,
+    utils::{
+        for_crate::bevy_egui::{egui, EguiContexts, EguiPopup, ExtendedBevyEguiContext},
+        plugins::console::{
+            ConsoleApp, ConsoleCommands, ConsoleHistory, ConsoleLineKind, PendingConsoleCommands,
+        },
+    },
+};
+use bevy::prelude::*;
+
+pub struct DevConsolePlugin;
+
+impl Plugin for DevConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevConsole>()
+            .add_systems(Update, (toggle_dev_console, draw_dev_console).chain())
+            .add_console_command("clear", "", "clear console output", |world, _args| {
+                world.resource_mut::<ConsoleHistory>().output.clear();
+                Ok(String::new())
+            });
+    }
+}
+
+const WINDOW_NAME: &str = "dev_console";
+
+#[derive(Resource, Default)]
+struct DevConsole {
+    visible: bool,
+    input: String,
+    /// Index in [`ConsoleHistory::inputs`] if browsing history
+    history_index: Option<usize>,
+    /// Input field must be focused
+    request_focus: bool,
+}
+
+fn toggle_dev_console(
+    mut console: ResMut<DevConsole>,
+    mut windows: ResMut<OpenWindows>,
+    toggle: WindowToggle,
+    mut egui: EguiContexts,
+) {
+    if toggle.just_pressed(UiAction::DevConsole) {
+        console.visible = !console.visible;
+        console.request_focus = console.visible;
+        windows.set(WINDOW_NAME, console.visible);
+
+        // toggle key may also type a character, it shouldn't get into any text field
+        egui.ctx_mut().input_mut(|input| {
+            input
+                .events
+                .retain(|event| !matches!(event, egui::Event::Text(_)))
+        });
+    }
+}
+
+fn line_color(kind: ConsoleLineKind) -> egui::Color32 {
+    match kind {
+        ConsoleLineKind::Input => egui::Color32::from_gray(160),
+        ConsoleLineKind::Output => egui::Color32::from_gray(220),
+        ConsoleLineKind::Error => egui::Color32::from_rgb(255, 90, 90),
+    }
+}
+
+/// Longest string which all strings start with
+fn common_prefix(strings: &[String]) -> &str {
+    let Some(first) = strings.first() else {
+        return "";
+    };
+    let mut len = first.len();
+    for string in &strings[1..] {
+        len = first
+            .char_indices()
+            .zip(string.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((index, a), _)| index + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(len);
+    }
+    &first[..len]
+}
+
+fn draw_dev_console(
+    mut egui: EguiContexts,
+    mut console: ResMut<DevConsole>,
+    mut pending: ResMut<PendingConsoleCommands>,
+    history: Res<ConsoleHistory>,
+    commands: Res<ConsoleCommands>,
+) {
+    if !console.visible {
+        return;
+    }
+    let console = &mut *console;
+
+    let popup = EguiPopup {
+        name: WINDOW_NAME,
+        anchor: egui::Align2::CENTER_TOP,
+        order: egui::Order::Foreground,
+        ..default()
+    };
+    egui.popup(popup, |ui| {
+        ui.set_width(800.);
+
+        egui::ScrollArea::vertical()
+            .max_height(300.)
+            .stick_to_bottom(true)
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for line in &history.output {
+                    ui.label(
+                        egui::RichText::new(&line.text)
+                            .monospace()
+                            .color(line_color(line.kind)),
+                    );
+                }
+            });
+        ui.separator();
+
+        let input_id = egui::Id::new("dev_console_input");
+        let mut cursor_to_end = false;
+
+        // handled before text edit, so it doesn't move cursor or focus
+        if ui.memory(|memory| memory.has_focus(input_id)) {
+            let (tab, up, down) = ui.input_mut(|input| {
+                (
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                    input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                )
+            });
+
+            if tab {
+                let names = commands.complete(&console.input);
+                let completed = match names.len() {
+                    0 => None,
+                    1 => Some(format!("{} ", names[0])),
+                    _ => Some(common_prefix(&names).to_string()),
+                };
+                if let Some(completed) = completed {
+                    if completed.len() > console.input.trim_start().len() {
+                        console.input = completed;
+                        cursor_to_end = true;
+                    }
+                }
+            }
+
+            if up || down {
+                let last = history.inputs.len().checked_sub(1);
+                console.history_index = match (console.history_index, up) {
+                    (None, true) => last,
+                    (None, false) => None,
+                    (Some(index), true) => Some(index.saturating_sub(1)),
+                    (Some(index), false) => Some(index + 1).filter(|index| Some(*index) <= last),
+                };
+                console.input = console
+                    .history_index
+                    .and_then(|index| history.inputs.get(index))
+                    .cloned()
+                    .unwrap_or_default();
+                cursor_to_end = true;
+            }
+        }
+
+        let output = egui::TextEdit::singleline(&mut console.input)
+            .id(input_id)
+            .lock_focus(true)
+            .font(egui::TextStyle::Monospace)
+            .desired_width(f32::INFINITY)
+            .hint_text("type \"help\" for the list of commands")
+            .show(ui);
+
+        let entered = output.response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        if entered {
+            let line = std::mem::take(&mut console.input);
+            if !line.trim().is_empty() {
+                pending.0.push(line);
+            }
+            console.history_index = None;
+        }
+
+        if entered || console.request_focus {
+            output.response.request_focus();
+            console.request_focus = false;
+        }
+        if cursor_to_end {
+            let mut state = output.state;
+            let end = egui::text::CCursor::new(console.input.chars().count());
+            state.set_ccursor_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ui.ctx(), input_id);
+        }
+
+        let input = console.input.trim_start();
+        if !input.is_empty() {
+            for name in commands.complete(input) {
+                if name != input.trim_end() {
+                    ui.label(
+                        egui::RichText::new(name)
+                            .monospace()
+                            .color(line_color(ConsoleLineKind::Input)),
+                    );
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_common_prefix() {
+        let strings = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(common_prefix(&[]), "");
+        assert_eq!(common_prefix(&strings(&["spawn"])), "spawn");
+        assert_eq!(
+            common_prefix(&strings(&["physics debug", "physics dump"])),
+            "physics d"
+        );
+        assert_eq!(common_prefix(&strings(&["set", "spawn"])), "s");
+        assert_eq!(common_prefix(&strings(&["set", "clear"])), "");
+    }
+}
@@ -0,0 +1,360 @@
+//! Window for tuning hovercrab parameters while playing
+
+use super::{OpenWindows, WindowToggle};
+use crate::{
+    gameplay::objects::hovercrab::{
+        Hovercrab, HovercrabParams, HovercrabStatus, HovercrabTuning, ThrusterLayout,
+    },
+    presentation::player::{actions::UiAction, local_player::LocalPlayer},
+    utils::{
+        file_utils::Validate,
+        for_crate::bevy_egui::{egui, EguiContexts, ExtendedEguiUi},
+    },
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::{RapierConfiguration, Velocity};
+use std::ops::RangeInclusive;
+
+pub struct HovercrabInspectorPlugin;
+
+impl Plugin for HovercrabInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HovercrabInspector>()
+            .add_systems(Update, (toggle_inspector, draw_inspector).chain());
+    }
+}
+
+const WINDOW_NAME: &str = "hovercrab_inspector";
+
+/// Upper limit of speed readout, meters per second
+const MAX_SPEED_VIEW: f32 = 100.;
+/// Limit of torque readouts, Newton-meters
+const MAX_TORQUE_VIEW: f32 = 50_000.;
+
+#[derive(Resource, Default)]
+struct HovercrabInspector {
+    visible: bool,
+    /// If none or despawned, player's hovercrab is selected
+    selected: Option<Entity>,
+    /// Result of the last edit or save
+    message: Option<Result<String, String>>,
+}
+
+fn toggle_inspector(
+    mut inspector: ResMut<HovercrabInspector>,
+    mut windows: ResMut<OpenWindows>,
+    toggle: WindowToggle,
+) {
+    if toggle.just_pressed(UiAction::HovercrabInspector) {
+        inspector.visible = !inspector.visible;
+        windows.set(WINDOW_NAME, inspector.visible);
+    }
+}
+
+fn draw_inspector(
+    mut egui: EguiContexts,
+    mut inspector: ResMut<HovercrabInspector>,
+    mut windows: ResMut<OpenWindows>,
+    mut crabs: Query<
+        (
+            Entity,
+            &mut HovercrabParams,
+            &HovercrabStatus,
+            &Velocity,
+            Option<&HovercrabTuning>,
+            Option<&LocalPlayer>,
+        ),
+        With<Hovercrab>,
+    >,
+    phy_config: Res<RapierConfiguration>,
+) {
+    if !inspector.visible {
+        return;
+    }
+    let inspector = &mut *inspector;
+
+    if !inspector
+        .selected
+        .is_some_and(|entity| crabs.contains(entity))
+    {
+        inspector.selected = crabs
+            .iter()
+            .max_by_key(|(.., player)| player.is_some())
+            .map(|(entity, ..)| entity);
+    }
+
+    let mut open = true;
+    egui::Window::new("Hovercrab tuning")
+        .open(&mut open)
+        .default_size([450., 700.])
+        .show(egui.ctx_mut(), |ui| {
+            let selected_text = match inspector.selected {
+                Some(entity) => format!("{entity:?}"),
+                None => "none".to_string(),
+            };
+            egui::ComboBox::from_label("Hovercrab")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for (entity, .., player) in crabs.iter() {
+                        let text = match player {
+                            Some(_) => format!("{entity:?} (player)"),
+                            None => format!("{entity:?}"),
+                        };
+                        ui.selectable_value(&mut inspector.selected, Some(entity), text);
+                    }
+                });
+
+            let Some(Ok((_, mut params, status, velocity, tuning, _))) =
+                inspector.selected.map(|entity| crabs.get_mut(entity))
+            else {
+                ui.label("No hovercrabs");
+                return;
+            };
+
+            ui.horizontal(|ui| {
+                let name = tuning.map(|tuning| tuning.0.as_str());
+
+                if ui.enabled_button(name.is_some(), "Save").clicked() {
+                    if let Some(name) = name {
+                        let filename = HovercrabParams::filename(name);
+                        inspector.message = Some(match params.save(name) {
+                            true => Ok(format!("Saved to \"{filename}\"")),
+                            false => Err(format!("Failed to save \"{filename}\"")),
+                        });
+                    }
+                }
+                if ui.enabled_button(name.is_some(), "Revert").clicked() {
+                    if let Some(name) = name {
+                        *params = HovercrabParams::load(name);
+                        inspector.message = None;
+                    }
+                }
+
+                match name {
+                    Some(name) => ui.label(HovercrabParams::filename(name)),
+                    None => ui.label("Hovercrab has no tuning file"),
+                };
+            });
+            match &inspector.message {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(egui::Color32::from_rgb(255, 90, 90), error);
+                }
+                None => (),
+            }
+
+            ui.named_group("Status", |ui| {
+                let speed = velocity.linvel.length();
+                ui.number_view(speed, 0., MAX_SPEED_VIEW, "speed, m/s");
+
+                let distances: Vec<_> = status.rays.iter().filter_map(|ray| ray.distance).collect();
+                let altitude = match distances.is_empty() {
+                    true => params.ray_length,
+                    false => distances.iter().sum::<f32>() / distances.len() as f32,
+                };
+                ui.number_view(altitude, 0., params.ray_length, "altitude, m");
+                ui.number_view(
+                    status.hover_height,
+                    params.min_hover_height,
+                    params.max_hover_height,
+                    "hover height, m",
+                );
+                ui.number_view(status.boost_energy, 0., params.boost_energy, "boost energy");
+                ui.number_view(status.hop_charge, 0., 1., "hop charge");
+
+                let axes = ["x", "y", "z"];
+                for (axis, torque) in axes.into_iter().zip(status.torque.to_array()) {
+                    ui.number_view(
+                        torque,
+                        -MAX_TORQUE_VIEW,
+                        MAX_TORQUE_VIEW,
+                        &format!("torque.{axis}, N*m"),
+                    );
+                }
+            });
+
+            ui.named_group("Thrusters", |ui| {
+                let ray_count = status.rays.len().max(1) as f32;
+                let gravity = phy_config.gravity.length();
+                let max_force = params.max_hover_force * gravity * params.mass / ray_count;
+
+                for (index, ray) in status.rays.iter().enumerate() {
+                    ui.number_view(
+                        ray.distance.unwrap_or(params.ray_length),
+                        0.,
+                        params.ray_length,
+                        &format!("#{index} distance, m"),
+                    );
+                    ui.number_view(ray.force, 0., max_force, &format!("#{index} force, N"));
+                }
+            });
+
+            // edited copy is applied only if valid
+            let mut new_params = params.clone();
+            let changed = egui::ScrollArea::vertical()
+                .show(ui, |ui| edit_params(ui, &mut new_params))
+                .inner;
+            if changed {
+                match new_params.validate() {
+                    Ok(()) => {
+                        *params = new_params;
+                        inspector.message = None;
+                    }
+                    Err(error) => inspector.message = Some(Err(error)),
+                }
+            }
+        });
+
+    if !open {
+        inspector.visible = false;
+        windows.set(WINDOW_NAME, false);
+    }
+}
+
+/// Returns true if anything changed
+fn edit_params(ui: &mut egui::Ui, params: &mut HovercrabParams) -> bool {
+    let mut changed = false;
+
+    ui.named_group("Body", |ui| {
+        changed |= edit_vec3(ui, "half_size", &mut params.half_size, 0.1..=10.);
+        ui.horizontal(|ui| {
+            ui.label("mass:");
+            changed |= ui.number_edit(&mut params.mass, 1);
+        });
+    });
+
+    ui.named_group("Rotation", |ui| {
+        changed |= ui.slider(
+            "rotation_speed_degrees",
+            &mut params.rotation_speed_degrees,
+            0. ..=720.,
+        );
+        changed |= ui.slider(
+            "rotation_acceleration_degrees",
+            &mut params.rotation_acceleration_degrees,
+            0. ..=1440.,
+        );
+        changed |= ui.slider("rotation_damping", &mut params.rotation_damping, 0. ..=50.);
+    });
+
+    ui.named_group("Thrusters", |ui| {
+        changed |= edit_thrusters(ui, &mut params.thrusters);
+        changed |= ui.slider("ray_offset", &mut params.ray_offset, 0. ..=1.5);
+        changed |= ui.slider("ray_margin", &mut params.ray_margin, 0. ..=1.);
+        changed |= ui.slider("ray_length", &mut params.ray_length, 0.1..=30.);
+        changed |= ui.slider("max_tilt_degrees", &mut params.max_tilt_degrees, 0. ..=90.);
+        changed |= ui.slider(
+            "thruster_stiffness",
+            &mut params.thruster_stiffness,
+            0. ..=100.,
+        );
+        changed |= ui.slider("thruster_damping", &mut params.thruster_damping, 0. ..=50.);
+        changed |= ui.slider("max_hover_force", &mut params.max_hover_force, 0. ..=10.);
+    });
+
+    ui.named_group("Alignment", |ui| {
+        changed |= ui.slider("align_stiffness", &mut params.align_stiffness, 0. ..=100.);
+        changed |= ui.slider("align_damping", &mut params.align_damping, 0. ..=50.);
+    });
+
+    ui.named_group("Aerodynamics", |ui| {
+        let aero = &mut params.aerodynamics;
+        changed |= edit_vec3(ui, "area", &mut aero.area, 0. ..=100.);
+        changed |= edit_vec3(ui, "drag_coeff", &mut aero.drag_coeff, 0. ..=2.);
+        changed |= ui.slider("lift_coeff", &mut aero.lift_coeff, -2. ..=2.);
+        changed |= edit_vec3(ui, "angular_damping", &mut aero.angular_damping, 0. ..=5.);
+    });
+
+    ui.named_group("Boost", |ui| {
+        changed |= ui.slider(
+            "boost_acceleration",
+            &mut params.boost_acceleration,
+            0. ..=50.,
+        );
+        changed |= ui.slider("boost_energy", &mut params.boost_energy, 0. ..=500.);
+        changed |= ui.slider(
+            "boost_energy_usage",
+            &mut params.boost_energy_usage,
+            0. ..=200.,
+        );
+        changed |= ui.slider(
+            "boost_energy_regen",
+            &mut params.boost_energy_regen,
+            0. ..=100.,
+        );
+    });
+
+    ui.named_group("Air-brake", |ui| {
+        changed |= ui.slider(
+            "brake_acceleration",
+            &mut params.brake_acceleration,
+            0. ..=50.,
+        );
+        changed |= ui.slider(
+            "brake_drag_multiplier",
+            &mut params.brake_drag_multiplier,
+            1. ..=20.,
+        );
+    });
+
+    ui.named_group("Hover height", |ui| {
+        changed |= ui.slider("hover_height", &mut params.hover_height, 0. ..=20.);
+        changed |= ui.slider("min_hover_height", &mut params.min_hover_height, 0. ..=20.);
+        changed |= ui.slider("max_hover_height", &mut params.max_hover_height, 0. ..=20.);
+        changed |= ui.slider(
+            "hover_height_speed",
+            &mut params.hover_height_speed,
+            0. ..=10.,
+        );
+    });
+
+    ui.named_group("Hop", |ui| {
+        changed |= ui.slider("hop_min_speed", &mut params.hop_min_speed, 0. ..=30.);
+        changed |= ui.slider("hop_max_speed", &mut params.hop_max_speed, 0. ..=30.);
+        changed |= ui.slider("hop_charge_time", &mut params.hop_charge_time, 0. ..=5.);
+        changed |= ui.slider("hop_cooldown", &mut params.hop_cooldown, 0. ..=10.);
+    });
+
+    changed
+}
+
+/// Slider for each component. Returns true if value changed.
+fn edit_vec3(ui: &mut egui::Ui, name: &str, value: &mut Vec3, range: RangeInclusive<f32>) -> bool {
+    let mut changed = false;
+    let components: &mut [f32; 3] = value.as_mut();
+    for (axis, component) in ["x", "y", "z"].into_iter().zip(components) {
+        changed |= ui.slider(&format!("{name}.{axis}"), component, range.clone());
+    }
+    changed
+}
+
+/// Returns true if layout changed. Custom layout can only be edited in file.
+fn edit_thrusters(ui: &mut egui::Ui, layout: &mut ThrusterLayout) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        let options = [
+            ("Sides", ThrusterLayout::Sides),
+            ("Corners", ThrusterLayout::Corners),
+            ("Grid", ThrusterLayout::Grid { x: 3, z: 3 }),
+        ];
+        for (name, option) in options {
+            let selected = std::mem::discriminant(&*layout) == std::mem::discriminant(&option);
+            if ui.radio(selected, name).clicked() && !selected {
+                *layout = option;
+                changed = true;
+            }
+        }
+        if let ThrusterLayout::Custom(positions) = layout {
+            ui.label(format!("Custom, {} thrusters", positions.len()));
+        }
+    });
+
+    if let ThrusterLayout::Grid { x, z } = layout {
+        changed |= ui.slider("x", x, 1..=8);
+        changed |= ui.slider("z", z, 1..=8);
+    }
+    changed
+}
@@ -0,0 +1,123 @@
+//! Window with last log messages
+
+use super::{OpenWindows, WindowToggle};
+use crate::{
+    presentation::player::actions::UiAction,
+    utils::{
+        for_crate::bevy_egui::{egui, EguiContexts},
+        plugins::logging::{LogFilter, LogMessages},
+    },
+};
+use bevy::{log::Level, prelude::*};
+
+pub struct LogConsolePlugin;
+
+impl Plugin for LogConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LogConsole>()
+            .add_systems(Update, (toggle_log_console, draw_log_console).chain());
+    }
+}
+
+const WINDOW_NAME: &str = "log_console";
+
+#[derive(Resource, Default)]
+struct LogConsole {
+    visible: bool,
+    /// Edited filter; none if not edited yet
+    filter: Option<String>,
+    filter_error: Option<String>,
+}
+
+fn toggle_log_console(
+    mut console: ResMut<LogConsole>,
+    mut windows: ResMut<OpenWindows>,
+    toggle: WindowToggle,
+) {
+    if toggle.just_pressed(UiAction::LogConsole) {
+        console.visible = !console.visible;
+        windows.set(WINDOW_NAME, console.visible);
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::ERROR => egui::Color32::from_rgb(255, 90, 90),
+        Level::WARN => egui::Color32::from_rgb(255, 220, 90),
+        Level::INFO => egui::Color32::from_gray(220),
+        Level::DEBUG => egui::Color32::from_gray(160),
+        _ => egui::Color32::from_gray(120), // trace
+    }
+}
+
+fn draw_log_console(
+    mut egui: EguiContexts,
+    mut console: ResMut<LogConsole>,
+    mut windows: ResMut<OpenWindows>,
+    messages: Option<Res<LogMessages>>,
+    log_filter: Option<ResMut<LogFilter>>,
+) {
+    if !console.visible {
+        return;
+    }
+    let console = &mut *console;
+
+    let mut open = true;
+    egui::Window::new("Log")
+        .open(&mut open)
+        .default_size([800., 400.])
+        .show(egui.ctx_mut(), |ui| {
+            if let Some(mut log_filter) = log_filter {
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+
+                    let filter = console
+                        .filter
+                        .get_or_insert_with(|| log_filter.current().to_string());
+                    let response = ui.add(
+                        egui::TextEdit::singleline(filter)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(400.),
+                    );
+                    let entered =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    if ui.button("Apply").clicked() || entered {
+                        console.filter_error = log_filter.set(filter).err();
+                    }
+                });
+                if let Some(error) = &console.filter_error {
+                    ui.colored_label(level_color(Level::ERROR), error);
+                }
+                ui.separator();
+            }
+
+            let Some(messages) = messages else {
+                ui.label("Logging plugin isn't enabled");
+                return;
+            };
+
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::both()
+                .stick_to_bottom(true)
+                .auto_shrink([false, false])
+                .show_rows(ui, row_height, messages.messages.len(), |ui, rows| {
+                    for message in messages.messages.range(rows) {
+                        let text = format!(
+                            "{} {:>5} {}: {}",
+                            message.time, message.level, message.target, message.text
+                        );
+                        ui.label(
+                            egui::RichText::new(text)
+                                .monospace()
+                                .color(level_color(message.level)),
+                        );
+                    }
+                });
+        });
+
+    if !open {
+        console.visible = false;
+        windows.set(WINDOW_NAME, false);
+    }
+}
@@ -0,0 +1,93 @@
+//! Debug and developer windows
+
+use crate::{
+    presentation::player::{
+        actions::{CameraAction, HovercrabAction, InputBindings, UiAction},
+        mouselook::InputControl,
+    },
+    utils::for_crate::bevy_egui::EguiContexts,
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+use leafwing_input_manager::prelude::*;
+
+pub mod dev_console;
+pub mod hovercrab_inspector;
+pub mod log_console;
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenWindows>()
+            .add_plugins((
+                log_console::LogConsolePlugin,
+                dev_console::DevConsolePlugin,
+                hovercrab_inspector::HovercrabInspectorPlugin,
+            ))
+            .add_systems(PostUpdate, update_input_control);
+    }
+}
+
+/// Windows which require mouse cursor. Mouselook is disabled while any is open.
+#[derive(Resource, Default)]
+pub struct OpenWindows(HashSet<&'static str>);
+
+impl OpenWindows {
+    pub fn set(&mut self, name: &'static str, open: bool) {
+        if open {
+            self.0.insert(name);
+        } else {
+            self.0.remove(name);
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+/// Checks if window toggle action was pressed.
+///
+/// [`UiAction`]s are disabled while typing, so then keys bound to the action
+/// are checked directly, to be able to close the window. Only single keys work
+/// in that case, not chords or gamepad buttons.
+#[derive(SystemParam)]
+pub struct WindowToggle<'w> {
+    actions: Res<'w, ActionState<UiAction>>,
+    toggle: Res<'w, ToggleActions<UiAction>>,
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, Input<KeyCode>>,
+}
+
+impl WindowToggle<'_> {
+    pub fn just_pressed(&self, action: UiAction) -> bool {
+        if self.toggle.enabled {
+            return self.actions.just_pressed(action);
+        }
+        self.bindings
+            .ui
+            .get(action)
+            .iter()
+            .any(|input| match input {
+                UserInput::Single(InputKind::Keyboard(key)) => self.keys.just_pressed(*key),
+                _ => false,
+            })
+    }
+}
+
+/// Disables mouselook while window is open, and all actions while typing
+fn update_input_control(
+    windows: Res<OpenWindows>,
+    mut input: ResMut<InputControl>,
+    mut egui: EguiContexts,
+    mut hovercrab_actions: ResMut<ToggleActions<HovercrabAction>>,
+    mut camera_actions: ResMut<ToggleActions<CameraAction>>,
+    mut ui_actions: ResMut<ToggleActions<UiAction>>,
+) {
+    input.mouselook_enabled = !windows.any();
+
+    let typing = egui.ctx_mut().wants_keyboard_input();
+    hovercrab_actions.enabled = !typing;
+    camera_actions.enabled = !typing;
+    ui_actions.enabled = !typing;
+}
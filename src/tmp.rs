@@ -1,40 +1,150 @@
 use crate::{
-    gameplay::objects::hovercrab::Hovercrab,
+    gameplay::objects::hovercrab::{Hovercrab, HovercrabTuning},
     presentation::player::{
+        actions::UiAction,
         camera::WorldCamera,
+        camera_modes::{CameraMode, CameraModes, TracksideCamera},
+        local_player::LocalPlayer,
         mouselook::{MouselookController, OrbitCamera},
     },
-    utils::{for_crate::bevy::FallibleCommands, plugins::scene_utils::SceneStaticCollider},
+    utils::{
+        for_crate::bevy::FallibleCommands,
+        plugins::{
+            console::ConsoleApp,
+            crash_report::set_crash_context,
+            file_watcher::{FileChanged, WatchedFiles},
+            scene_utils::SceneStaticCollider,
+        },
+    },
 };
-use bevy::prelude::*;
+use bevy::{asset::HandleId, prelude::*};
+use leafwing_input_manager::prelude::*;
 
 pub struct TmpPlugin;
 
 impl Plugin for TmpPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (initial_spawn,))
-            .add_systems(Update, (spawn_player,));
+        app.init_resource::<SpawnRequest>()
+            .add_systems(Startup, watch_level)
+            .add_systems(Update, (reload_level, spawn_level, spawn_player).chain())
+            .add_console_command(
+                "reload level",
+                "",
+                "respawn level and player",
+                |world, _args| {
+                    *world.resource_mut::<SpawnRequest>() = default();
+                    Ok(String::new())
+                },
+            );
+    }
+}
+
+/// What should be (re)spawned
+#[derive(Resource)]
+struct SpawnRequest {
+    level: bool,
+    player: bool,
+}
+
+impl Default for SpawnRequest {
+    fn default() -> Self {
+        Self {
+            level: true,
+            player: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct LevelObject;
+
+/// Level model, relative to assets directory
+const LEVEL: &str = "models/ground.gltf";
+
+fn level_filename() -> String {
+    format!("assets/{LEVEL}")
+}
+
+fn watch_level(mut files: ResMut<WatchedFiles>) {
+    files.watch(&level_filename());
+}
+
+/// Reload model when its file changes, and respawn level once it's loaded
+fn reload_level(
+    mut changes: EventReader<FileChanged>,
+    mut scene_events: EventReader<AssetEvent<Scene>>,
+    mut request: ResMut<SpawnRequest>,
+    asset_server: Res<AssetServer>,
+) {
+    if changes
+        .iter()
+        .any(|change| change.filename == level_filename())
+    {
+        info!("Reloading level \"{LEVEL}\"");
+        asset_server.reload_asset(LEVEL);
+    }
+
+    let scene_id = HandleId::from(format!("{LEVEL}#Scene0"));
+    for event in scene_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if handle.id() == scene_id {
+                request.level = true;
+            }
+        }
     }
 }
 
-fn initial_spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_level(
+    mut commands: Commands,
+    objects: Query<Entity, With<LevelObject>>,
+    mut request: ResMut<SpawnRequest>,
+    asset_server: Res<AssetServer>,
+) {
+    if !request.level {
+        return;
+    }
+    request.level = false;
+
+    for entity in objects.iter() {
+        commands.try_despawn_recursive(entity);
+    }
+
+    set_crash_context("level", LEVEL);
+
     // ground & sun
     commands.spawn((
+        LevelObject,
         SceneBundle {
-            scene: asset_server.load("models/ground.gltf#Scene0"),
+            scene: asset_server.load(format!("{LEVEL}#Scene0")),
             ..default()
         },
         SceneStaticCollider,
     ));
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            illuminance: 50_000.,
-            shadows_enabled: true,
+    commands.spawn((
+        LevelObject,
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: 50_000.,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::default().looking_to(Vec3::new(0.1, -0.9, -0.2), Vec3::Y),
             ..default()
         },
-        transform: Transform::default().looking_to(Vec3::new(0.1, -0.9, -0.2), Vec3::Y),
-        ..default()
-    });
+    ));
+
+    for position in [
+        Vec3::new(30., 8., 30.),
+        Vec3::new(-30., 8., 30.),
+        Vec3::new(30., 8., -30.),
+        Vec3::new(-30., 8., -30.),
+    ] {
+        commands.spawn((
+            LevelObject,
+            TracksideCamera,
+            TransformBundle::from_transform(Transform::from_translation(position)),
+        ));
+    }
 }
 
 #[derive(Component)]
@@ -43,11 +153,11 @@ struct GameplayObject;
 fn spawn_player(
     mut commands: Commands,
     objects: Query<Entity, With<GameplayObject>>,
-    keys: Res<Input<KeyCode>>,
-    mut inited: Local<bool>,
+    actions: Res<ActionState<UiAction>>,
+    mut request: ResMut<SpawnRequest>,
 ) {
-    if keys.just_pressed(KeyCode::R) || !*inited {
-        *inited = true;
+    if actions.just_pressed(UiAction::Respawn) || request.player {
+        request.player = false;
 
         for entity in objects.iter() {
             commands.try_despawn_recursive(entity);
@@ -65,14 +175,20 @@ fn spawn_player(
                 GameplayObject,
                 WorldCamera,
                 MouselookController { allow_flip: false },
-                OrbitCamera {
-                    target: player_entity.into(),
-                    distance: 12.,
-                    offset: Vec3::new(0., 3., 0.),
-                },
+                OrbitCamera::new(player_entity.into(), Vec3::new(0., 3., 0.)),
+                CameraModes::new(CameraMode::Orbit, player_entity.into()),
             ))
             .id();
 
-        commands.try_insert(player_entity, Hovercrab::new(camera_entity));
+        commands.try_insert(
+            player_entity,
+            (
+                Hovercrab,
+                LocalPlayer {
+                    camera: camera_entity,
+                },
+                HovercrabTuning("default".to_string()),
+            ),
+        );
     }
 }
@@ -4,9 +4,12 @@ use super::for_crate::std::ExtendedStdResult;
 use serde::{de::DeserializeOwned, Serialize};
 
 /// Write value to file with pretty RON. All errors are returned as strings.
+///
+/// Parent directories are created if they don't exist.
 pub fn save_ron_file<T: Serialize>(value: &T, filename: &str) -> bool {
     ron::ser::to_string_pretty(value, Default::default())
         .map_err_to_string()
+        .and_then(|data| create_parent_dirs(filename).map(|_| data))
         .and_then(|data| std::fs::write(&filename, data).map_err_to_string())
         .map_err(|e| format!("{} [file \"{}\"]", e.to_string(), filename))
         .ok_or_log_err()
@@ -22,6 +25,37 @@ pub fn load_ron_file<T: DeserializeOwned>(filename: &str) -> Option<T> {
         .ok_or_log_err()
 }
 
+/// Value which can be checked for errors after being loaded from a file.
+pub trait Validate {
+    /// Returns description of the error if value is invalid.
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Returns error if any of the named values is NaN or infinite. Should be used
+/// in [`Validate::validate`] before other checks, since comparisons with NaN are
+/// always false.
+pub fn check_finite<'a>(values: impl IntoIterator<Item = (&'a str, f32)>) -> Result<(), String> {
+    for (name, value) in values {
+        if !value.is_finite() {
+            return Err(format!("{name} must be finite"));
+        }
+    }
+    Ok(())
+}
+
+/// Read value from RON file and validate it. All errors are logged.
+pub fn load_valid_ron_file<T: DeserializeOwned + Validate>(filename: &str) -> Option<T> {
+    load_ron_file::<T>(filename).and_then(|value| {
+        value
+            .validate()
+            .map(|_| value)
+            .map_err(|e| format!("{} [file \"{}\"]", e, filename))
+            .ok_or_log_err()
+    })
+}
+
 /// Read value from RON file, on error consider it to be default-initialized.
 ///
 /// All errors are logged.
@@ -34,3 +68,12 @@ pub fn load_and_update_ron_file<T: Serialize + DeserializeOwned + Default>(filen
     save_ron_file(&value, filename);
     value
 }
+
+fn create_parent_dirs(filename: &str) -> Result<(), String> {
+    match std::path::Path::new(filename).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => {
+            std::fs::create_dir_all(dir).map_err_to_string()
+        }
+        _ => Ok(()),
+    }
+}
@@ -103,7 +103,7 @@ pub trait ExtendedEguiUi {
 
     /// Barely working single-line text input for numbers.
     ///
-    /// Returns true if value changed.
+    /// Returns true if value changed. NaN and infinity are ignored.
     fn number_edit(&mut self, value: &mut f32, precision: usize) -> bool;
 
     /// Shows slider-like bar with value and text
@@ -178,9 +178,11 @@ impl ExtendedEguiUi for egui::Ui {
     fn number_edit(&mut self, value: &mut f32, precision: usize) -> bool {
         let mut text = format!("{:.*}", precision, value);
         if self.text_edit_singleline(&mut text).changed() {
-            if let Ok(new_value) = text.parse() {
-                *value = new_value;
-                return true;
+            if let Ok(new_value) = text.parse::<f32>() {
+                if new_value.is_finite() {
+                    *value = new_value;
+                    return true;
+                }
             }
         }
         false
@@ -191,7 +193,13 @@ impl ExtendedEguiUi for egui::Ui {
             let text = format!("{text} {value:07.3}");
             ui.label(text);
 
-            let mut t = ((value - min) / (max - min)).clamp(0., 1.);
+            // slider can't show empty range or NaN
+            let ratio = (value - min) / (max - min);
+            let mut t = if max > min && !ratio.is_nan() {
+                ratio.clamp(0., 1.)
+            } else {
+                0.
+            };
             ui.add(egui::Slider::new(&mut t, 0. ..=1.).show_value(false));
         });
     }
@@ -6,7 +6,7 @@ use bevy::log::prelude::*;
 pub trait ExtendedStdResult<T> {
     fn map_err_to_string(self) -> Result<T, String>;
 
-    /// Logs error
+    /// Logs error. With `trace` feature, also logs current spans.
     fn ok_or_log_err(self) -> Option<T>;
 }
 
@@ -19,6 +19,13 @@ impl<T, E: ToString> ExtendedStdResult<T> for Result<T, E> {
         match self {
             Ok(value) => Some(value),
             Err(error) => {
+                #[cfg(feature = "trace")]
+                error!(
+                    "{}\n{}",
+                    error.to_string(),
+                    tracing_error::SpanTrace::capture()
+                );
+                #[cfg(not(feature = "trace"))]
                 error!("{}", error.to_string());
                 None
             }
@@ -74,6 +74,59 @@ pub fn change_angle_constant_speed(current: f32, target: f32, speed: f32) -> f32
     current + magnitude.copysign(delta)
 }
 
+/// Speed with which to move towards target, so it's possible to stop exactly at
+/// it with given constant deceleration. Sign is the same as of `distance`.
+pub fn approach_speed(distance: f32, max_speed: f32, deceleration: f32) -> f32 {
+    (2. * deceleration * distance.abs())
+        .sqrt()
+        .min(max_speed)
+        .copysign(distance)
+}
+
+/// Moment of inertia around the axis passing through center of mass.
+///
+/// `principal_frame` is rotation of the principal axes, same space as `axis`.
+/// Axis must be normalized.
+pub fn inertia_around_axis(principal_inertia: Vec3, principal_frame: Quat, axis: Vec3) -> f32 {
+    let local_axis = principal_frame.inverse() * axis;
+    principal_inertia.dot(local_axis * local_axis)
+}
+
+/// Apply radial deadzone and response curve to the gamepad stick position.
+///
+/// Output length is from zero at the edge of deadzone to one at full
+/// deflection, raised to the power of `exponent`.
+pub fn stick_response(value: Vec2, deadzone: f32, exponent: f32) -> Vec2 {
+    let length = value.length();
+    if length <= deadzone || deadzone >= 1. {
+        return Vec2::ZERO;
+    }
+    let t = ((length - deadzone) / (1. - deadzone)).min(1.);
+    value / length * t.powf(exponent)
+}
+
+/// Critically damped spring: moves `current` towards `target` without
+/// overshooting. Returns new value.
+///
+/// `velocity` is state of the spring, initially should be zero. `stiffness` is
+/// inverse of the time in which most of the distance is covered.
+pub fn smooth_damp<T>(current: T, target: T, velocity: &mut T, stiffness: f32, delta: f32) -> T
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>,
+{
+    // source: Game Programming Gems 4, chapter 1.10
+
+    let x = stiffness * delta;
+    let exp = 1. / (1. + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + change * stiffness) * delta;
+    *velocity = (*velocity - temp * stiffness) * exp;
+    target + (change + temp) * exp
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -155,4 +208,95 @@ mod test {
         assert_relative_eq!(fun(-PI / 2., PI / 3., PI * 2.), PI / 3., epsilon = ERROR);
         assert_relative_eq!(fun(PI / 2., -PI / 3., PI * 2.), -PI / 3., epsilon = ERROR);
     }
+
+    #[test]
+    fn approach_speed() {
+        let fun = super::approach_speed;
+
+        assert_relative_eq!(fun(0., 10., 1.), 0., epsilon = ERROR);
+        assert_relative_eq!(fun(2., 10., 1.), 2., epsilon = ERROR);
+        assert_relative_eq!(fun(-2., 10., 1.), -2., epsilon = ERROR);
+        assert_relative_eq!(fun(8., 10., 4.), 8., epsilon = ERROR);
+        assert_relative_eq!(fun(100., 10., 1.), 10., epsilon = ERROR); // limited
+        assert_relative_eq!(fun(-100., 10., 1.), -10., epsilon = ERROR);
+    }
+
+    #[test]
+    fn inertia_around_axis() {
+        let fun = super::inertia_around_axis;
+        let inertia = Vec3::new(1., 2., 3.);
+
+        assert_relative_eq!(fun(inertia, Quat::IDENTITY, Vec3::X), 1., epsilon = ERROR);
+        assert_relative_eq!(fun(inertia, Quat::IDENTITY, Vec3::Y), 2., epsilon = ERROR);
+        assert_relative_eq!(fun(inertia, Quat::IDENTITY, Vec3::Z), 3., epsilon = ERROR);
+
+        let frame = Quat::from_rotation_z(PI / 2.); // X becomes Y
+        assert_relative_eq!(fun(inertia, frame, Vec3::Y), 1., epsilon = ERROR);
+        assert_relative_eq!(fun(inertia, frame, Vec3::X), 2., epsilon = ERROR);
+
+        let diagonal = Vec3::new(1., 1., 0.).normalize();
+        assert_relative_eq!(fun(inertia, Quat::IDENTITY, diagonal), 1.5, epsilon = ERROR);
+    }
+
+    #[test]
+    fn stick_response() {
+        let fun = super::stick_response;
+
+        // deadzone
+        assert_relative_eq!(fun(Vec2::ZERO, 0.2, 1.), Vec2::ZERO, epsilon = ERROR);
+        assert_relative_eq!(
+            fun(Vec2::new(0.1, 0.1), 0.2, 1.),
+            Vec2::ZERO,
+            epsilon = ERROR
+        );
+
+        // linear
+        assert_relative_eq!(
+            fun(Vec2::new(0.6, 0.), 0.2, 1.),
+            Vec2::new(0.5, 0.),
+            epsilon = ERROR
+        );
+        assert_relative_eq!(
+            fun(Vec2::new(0., -1.), 0.2, 1.),
+            Vec2::new(0., -1.),
+            epsilon = ERROR
+        );
+        assert_relative_eq!(
+            fun(Vec2::new(0., -2.), 0.2, 1.),
+            Vec2::new(0., -1.),
+            epsilon = ERROR
+        );
+
+        // curve
+        assert_relative_eq!(
+            fun(Vec2::new(0.6, 0.), 0.2, 2.),
+            Vec2::new(0.25, 0.),
+            epsilon = ERROR
+        );
+    }
+
+    #[test]
+    fn smooth_damp() {
+        let fun = super::smooth_damp::<f32>;
+
+        // stays at target
+        let mut velocity = 0.;
+        assert_relative_eq!(fun(1., 1., &mut velocity, 5., 0.1), 1., epsilon = ERROR);
+        assert_relative_eq!(velocity, 0., epsilon = ERROR);
+
+        // approaches target without overshooting
+        let mut value = 0.;
+        let mut velocity = 0.;
+        let mut previous = value;
+        for _ in 0..200 {
+            value = fun(value, 10., &mut velocity, 5., 1. / 60.);
+            assert!(value >= previous && value <= 10.);
+            previous = value;
+        }
+        assert_relative_eq!(value, 10., epsilon = 0.01);
+
+        // zero delta doesn't change anything
+        let mut velocity = 0.;
+        assert_relative_eq!(fun(0., 10., &mut velocity, 5., 0.), 0., epsilon = ERROR);
+    }
 }
@@ -0,0 +1,349 @@
+//! Registry of developer console commands. UI is not included.
+
+use bevy::{prelude::*, reflect::GetPath};
+
+/// Command handler. Receives arguments (without command name), returns message
+/// or error.
+pub type ConsoleCommandFn =
+    Box<dyn Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync>;
+
+struct ConsoleCommand {
+    /// One or more words
+    name: String,
+    /// Description of arguments
+    args: String,
+    description: String,
+    handler: ConsoleCommandFn,
+}
+
+/// Built-in command which shows [`ConsoleCommands::help`]
+const HELP_COMMAND: &str = "help";
+
+/// All registered commands, see [`ConsoleApp::add_console_command`]
+#[derive(Resource, Default)]
+pub struct ConsoleCommands {
+    commands: Vec<ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    /// Find command with the longest name matching first words.
+    ///
+    /// Returns command and remaining words.
+    fn find<'a, 'b>(&'a self, words: &'b [&'b str]) -> Option<(&'a ConsoleCommand, &'b [&'b str])> {
+        self.commands
+            .iter()
+            .filter_map(|command| {
+                let name_len = command.name.split_whitespace().count();
+                let matches = words.len() >= name_len
+                    && command
+                        .name
+                        .split_whitespace()
+                        .eq(words[..name_len].iter().copied());
+                matches.then(|| (command, &words[name_len..]))
+            })
+            .max_by_key(|(command, _)| command.name.len())
+    }
+
+    /// Names of commands (including built-in `help`) which start with the input
+    pub fn complete(&self, input: &str) -> Vec<String> {
+        let input = input.trim_start();
+        let mut names: Vec<_> = self
+            .commands
+            .iter()
+            .map(|command| command.name.as_str())
+            .chain([HELP_COMMAND])
+            .filter(|name| name.starts_with(input))
+            .map(|name| name.to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Usage and description of each command
+    pub fn help(&self) -> String {
+        let mut commands: Vec<_> = self.commands.iter().collect();
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        commands
+            .iter()
+            .map(|command| {
+                format!(
+                    "{} {} - {}",
+                    command.name, command.args, command.description
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Output of executed commands
+#[derive(Resource, Default)]
+pub struct ConsoleHistory {
+    /// Previously entered lines, oldest first
+    pub inputs: Vec<String>,
+    pub output: Vec<ConsoleLine>,
+}
+
+pub struct ConsoleLine {
+    pub text: String,
+    pub kind: ConsoleLineKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleLineKind {
+    Input,
+    Output,
+    Error,
+}
+
+/// Lines which will be executed at the end of the frame
+#[derive(Resource, Default)]
+pub struct PendingConsoleCommands(pub Vec<String>);
+
+/// Adds methods to [`App`] for console commands.
+pub trait ConsoleApp {
+    /// Register command. Name may consist of several words, i.e. `spawn
+    /// hovercrab`. `args` is shown in help, i.e. `<x> <y> <z>`.
+    fn add_console_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        description: &str,
+        handler: impl Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+impl ConsoleApp for App {
+    fn add_console_command(
+        &mut self,
+        name: &str,
+        args: &str,
+        description: &str,
+        handler: impl Fn(&mut World, &[&str]) -> Result<String, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .commands
+            .push(ConsoleCommand {
+                name: name.split_whitespace().collect::<Vec<_>>().join(" "),
+                args: args.to_string(),
+                description: description.to_string(),
+                handler: Box::new(handler),
+            });
+        self
+    }
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>()
+            .init_resource::<ConsoleHistory>()
+            .init_resource::<PendingConsoleCommands>()
+            .add_systems(Last, run_pending_commands);
+    }
+}
+
+/// Execute single line. Result is also logged.
+pub fn execute_console_command(world: &mut World, line: &str) -> Result<String, String> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    if words.is_empty() {
+        return Ok(String::new());
+    }
+
+    let result = world.resource_scope(|world, commands: Mut<ConsoleCommands>| {
+        if words == [HELP_COMMAND] {
+            return Ok(commands.help());
+        }
+        let (command, args) = commands
+            .find(&words)
+            .ok_or_else(|| format!("Unknown command \"{line}\", type \"help\" for the list"))?;
+        (command.handler)(world, args)
+    });
+
+    match &result {
+        Ok(message) => info!("> {line}: {message}"),
+        Err(error) => warn!("> {line}: {error}"),
+    }
+    result
+}
+
+fn run_pending_commands(world: &mut World) {
+    let lines = std::mem::take(&mut world.resource_mut::<PendingConsoleCommands>().0);
+
+    for line in lines {
+        let result = execute_console_command(world, &line);
+
+        let mut history = world.resource_mut::<ConsoleHistory>();
+        if history.inputs.last() != Some(&line) {
+            history.inputs.push(line.clone());
+        }
+        history.output.push(ConsoleLine {
+            text: format!("> {line}"),
+            kind: ConsoleLineKind::Input,
+        });
+
+        let (text, kind) = match result {
+            Ok(text) => (text, ConsoleLineKind::Output),
+            Err(text) => (text, ConsoleLineKind::Error),
+        };
+        if !text.is_empty() {
+            history.output.push(ConsoleLine { text, kind });
+        }
+    }
+}
+
+/// Parse arguments as finite numbers
+pub fn parse_console_args<const N: usize>(args: &[&str]) -> Result<[f32; N], String> {
+    if args.len() != N {
+        return Err(format!("Expected {N} arguments, got {}", args.len()));
+    }
+    let mut values = [0.; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .ok()
+            .filter(|value: &f32| value.is_finite())
+            .ok_or_else(|| format!("\"{arg}\" is not a number"))?;
+    }
+    Ok(values)
+}
+
+/// Set field of reflected value from string, i.e. `set_reflect_field(params,
+/// "aerodynamics.lift_coeff", "0.5")`. Supports numbers and booleans; NaN and
+/// infinity are rejected.
+pub fn set_reflect_field(value: &mut dyn Reflect, path: &str, text: &str) -> Result<(), String> {
+    if text.parse::<f64>().is_ok_and(|number| !number.is_finite()) {
+        return Err(format!("Value of \"{path}\" must be finite"));
+    }
+    let field = value
+        .reflect_path_mut(path)
+        .map_err(|error| format!("Invalid field \"{path}\": {error}"))?;
+
+    macro_rules! try_parse {
+        ($($type:ty),*) => {
+            $(
+                if let Some(field) = field.downcast_mut::<$type>() {
+                    *field = text
+                        .parse()
+                        .map_err(|error| format!("Invalid value for \"{path}\": {error}"))?;
+                    return Ok(());
+                }
+            )*
+        };
+    }
+    try_parse!(f32, f64, bool, i32, u32, usize);
+
+    Err(format!(
+        "Field \"{path}\" has unsupported type {}",
+        field.type_name()
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn commands(names: &[&str]) -> ConsoleCommands {
+        let mut app = App::new();
+        for name in names {
+            app.add_console_command(name, "", "", |_, _| Ok(String::new()));
+        }
+        app.world.remove_resource::<ConsoleCommands>().unwrap()
+    }
+
+    #[test]
+    fn find_longest_name() {
+        for names in [["physics", "physics debug"], ["physics debug", "physics"]] {
+            let commands = commands(&names);
+            let find = |words: &[&str]| {
+                commands
+                    .find(words)
+                    .map(|(command, args)| (command.name.clone(), args.to_vec()))
+            };
+
+            assert_eq!(
+                find(&["physics", "debug", "on"]),
+                Some(("physics debug".to_string(), vec!["on"]))
+            );
+            assert_eq!(
+                find(&["physics", "dump"]),
+                Some(("physics".to_string(), vec!["dump"]))
+            );
+            assert_eq!(find(&["physics"]), Some(("physics".to_string(), vec![])));
+            assert_eq!(find(&["phys"]), None);
+            assert_eq!(find(&["debug"]), None);
+        }
+    }
+
+    #[test]
+    fn complete_names() {
+        let commands = commands(&["spawn hovercrab", "set", "physics debug"]);
+        assert_eq!(commands.complete("s"), ["set", "spawn hovercrab"]);
+        assert_eq!(commands.complete("  spawn h"), ["spawn hovercrab"]);
+        assert_eq!(commands.complete("h"), ["help"]);
+        assert_eq!(
+            commands.complete(""),
+            ["help", "physics debug", "set", "spawn hovercrab"]
+        );
+        assert!(commands.complete("x").is_empty());
+    }
+
+    #[test]
+    fn parse_args() {
+        assert_eq!(parse_console_args(&["1", "-2.5", "3"]), Ok([1., -2.5, 3.]));
+        assert_eq!(parse_console_args::<0>(&[]), Ok([]));
+        assert!(parse_console_args::<2>(&["1"]).is_err());
+        assert!(parse_console_args::<1>(&["1", "2"]).is_err());
+        assert!(parse_console_args::<2>(&["1", "x"]).is_err());
+        assert!(parse_console_args::<1>(&["1,5"]).is_err());
+        assert!(parse_console_args::<1>(&["nan"]).is_err());
+        assert!(parse_console_args::<2>(&["inf", "-inf"]).is_err());
+    }
+
+    #[derive(Reflect, Default)]
+    struct Inner {
+        value: f32,
+        enabled: bool,
+    }
+
+    #[derive(Reflect, Default)]
+    struct Outer {
+        inner: Inner,
+        count: u32,
+        name: String,
+    }
+
+    #[test]
+    fn set_fields() {
+        let mut value = Outer::default();
+
+        set_reflect_field(&mut value, "inner.value", "0.5").unwrap();
+        set_reflect_field(&mut value, "inner.enabled", "true").unwrap();
+        set_reflect_field(&mut value, "count", "3").unwrap();
+        assert_eq!(value.inner.value, 0.5);
+        assert!(value.inner.enabled);
+        assert_eq!(value.count, 3);
+
+        // wrong type
+        assert!(set_reflect_field(&mut value, "inner.value", "abc").is_err());
+        assert!(set_reflect_field(&mut value, "inner.enabled", "1").is_err());
+        assert!(set_reflect_field(&mut value, "count", "-1").is_err());
+        assert!(set_reflect_field(&mut value, "name", "crab").is_err());
+        assert!(set_reflect_field(&mut value, "inner", "1").is_err());
+        assert!(set_reflect_field(&mut value, "inner.value", "NaN").is_err());
+        assert!(set_reflect_field(&mut value, "inner.value", "-inf").is_err());
+
+        // unknown field
+        assert!(set_reflect_field(&mut value, "inner.missing", "1").is_err());
+        assert!(set_reflect_field(&mut value, "missing", "1").is_err());
+        assert!(set_reflect_field(&mut value, "count.value", "1").is_err());
+
+        // nothing changed on error
+        assert_eq!(value.inner.value, 0.5);
+        assert!(value.inner.enabled);
+        assert_eq!(value.count, 3);
+        assert!(value.name.is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+//! Writing crash report on panic
+
+use crate::utils::{
+    for_crate::std::ExtendedStdResult,
+    plugins::{logging, settings::SETTINGS_DIRECTORY},
+};
+use bevy::prelude::*;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Crash reports are in this directory
+pub const CRASH_REPORT_DIRECTORY: &str = "crash_reports";
+
+/// On panic writes crash report file to [`CRASH_REPORT_DIRECTORY`] and prints
+/// its path. Report includes backtrace, last log lines (see
+/// [`logging::recent_log_lines`]), [`set_crash_context`] values and current
+/// settings (see [`set_crash_settings`]). If no settings were set, settings
+/// files are included instead.
+///
+/// Only the first panic is reported.
+pub struct CrashReportPlugin;
+
+impl Plugin for CrashReportPlugin {
+    fn build(&self, _app: &mut App) {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            previous_hook(info);
+
+            static REPORTED: AtomicBool = AtomicBool::new(false);
+            if REPORTED.swap(true, Ordering::SeqCst) {
+                return;
+            }
+
+            // log may be broken at this point
+            match write_crash_report(info) {
+                Ok(filename) => eprintln!("Crash report saved to \"{filename}\""),
+                Err(error) => eprintln!("Failed to write crash report: {error}"),
+            }
+        }));
+    }
+}
+
+lazy_static! {
+    static ref CONTEXT: Mutex<BTreeMap<String, String>> = default();
+    static ref SETTINGS: Mutex<BTreeMap<String, String>> = default();
+}
+
+/// Set value which will be included in crash report (i.e. current level)
+pub fn set_crash_context(key: &str, value: impl Into<String>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.insert(key.to_string(), value.into());
+    }
+}
+
+/// Set current value of settings (or other tuning parameters), which will be
+/// included in crash report as RON. Should be called each time it changes.
+pub fn set_crash_settings(name: &str, value: &impl Serialize) {
+    let Some(data) = ron::ser::to_string_pretty(value, default())
+        .map_err(|error| format!("Failed to serialize \"{name}\" for crash report: {error}"))
+        .ok_or_log_err()
+    else {
+        return
+    };
+    if let Ok(mut settings) = SETTINGS.lock() {
+        settings.insert(name.to_string(), data);
+    }
+}
+
+/// Remove value set by [`set_crash_settings`]
+pub fn remove_crash_settings(name: &str) {
+    if let Ok(mut settings) = SETTINGS.lock() {
+        settings.remove(name);
+    }
+}
+
+fn write_crash_report(info: &std::panic::PanicInfo) -> Result<String, String> {
+    let mut report = String::new();
+
+    let _ = writeln!(
+        report,
+        "Crash report, {}",
+        chrono::Local::now().to_rfc2822()
+    );
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "\n{info}");
+
+    let _ = writeln!(report, "\n## Backtrace\n");
+    let _ = writeln!(report, "{}", std::backtrace::Backtrace::force_capture());
+
+    #[cfg(feature = "trace")]
+    {
+        let _ = writeln!(report, "\n## Spans\n");
+        let _ = writeln!(report, "{}", tracing_error::SpanTrace::capture());
+    }
+
+    let _ = writeln!(report, "\n## Context\n");
+    // panic might have happened while context was locked
+    if let Ok(context) = CONTEXT.try_lock() {
+        for (key, value) in context.iter() {
+            let _ = writeln!(report, "{key}: {value}");
+        }
+    }
+
+    let _ = writeln!(report, "\n## Log\n");
+    for line in logging::recent_log_lines() {
+        let _ = writeln!(report, "{line}");
+    }
+
+    let _ = writeln!(report, "\n## Settings\n");
+    let settings = SETTINGS
+        .try_lock()
+        .map(|settings| settings.clone())
+        .unwrap_or_default();
+    if settings.is_empty() {
+        append_settings(&mut report, Path::new(SETTINGS_DIRECTORY));
+    }
+    for (name, data) in settings {
+        let _ = writeln!(report, "### {name}\n\n{data}\n");
+    }
+
+    std::fs::create_dir_all(CRASH_REPORT_DIRECTORY).map_err(|error| error.to_string())?;
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let filename = format!("{CRASH_REPORT_DIRECTORY}/{timestamp}.txt");
+    std::fs::write(&filename, report).map_err(|error| error.to_string())?;
+
+    let filename = std::fs::canonicalize(&filename)
+        .map(|path| path.display().to_string())
+        .unwrap_or(filename);
+    Ok(filename)
+}
+
+/// Append contents of all files in the directory and its subdirectories
+fn append_settings(report: &mut String, directory: &Path) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|e| e.path())
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            append_settings(report, &path);
+        } else if let Ok(data) = std::fs::read_to_string(&path) {
+            let _ = writeln!(report, "### {}\n\n{data}\n", path.display());
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Reloading files when they change on disk
+
+use crate::utils::file_utils::{self, Validate};
+use bevy::{prelude::*, utils::HashMap};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{marker::PhantomData, time::SystemTime};
+
+/// Files which are checked for changes.
+///
+/// All registered files are checked periodically; if modification time
+/// changed, [`FileChanged`] is sent.
+#[derive(Resource, Default)]
+pub struct WatchedFiles {
+    /// Last known modification time
+    files: HashMap<String, Option<SystemTime>>,
+}
+
+impl WatchedFiles {
+    /// Start watching the file. Does nothing if it's already watched.
+    pub fn watch(&mut self, filename: &str) {
+        if !self.files.contains_key(filename) {
+            self.files
+                .insert(filename.to_string(), modification_time(filename));
+        }
+    }
+
+    /// Ignore current changes of the file, if it's watched.
+    ///
+    /// Useful when file is written by the game itself.
+    pub fn ignore_changes(&mut self, filename: &str) {
+        if let Some(last_modified) = self.files.get_mut(filename) {
+            *last_modified = modification_time(filename);
+        }
+    }
+}
+
+/// Sent when watched file is modified, created or removed
+#[derive(Event)]
+pub struct FileChanged {
+    pub filename: String,
+}
+
+/// Sent when resource was reloaded from the file.
+///
+/// See [`WatchedRonResourceApp::add_watched_ron_resource`].
+#[derive(Event)]
+pub struct ResourceReloaded<T> {
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for ResourceReloaded<T> {
+    fn default() -> Self {
+        Self { _marker: default() }
+    }
+}
+
+/// How often files are checked, in seconds
+const CHECK_INTERVAL: f32 = 0.5;
+
+pub struct FileWatcherPlugin;
+
+impl Plugin for FileWatcherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WatchedFiles>()
+            .add_event::<FileChanged>()
+            .add_systems(First, check_watched_files);
+    }
+}
+
+/// Adds methods to [`App`] for resources loaded from files.
+pub trait WatchedRonResourceApp {
+    /// Load resource from RON file and reload it each time the file changes.
+    ///
+    /// If file can't be loaded at startup, resource is default-initialized and
+    /// file is created. If it can't be loaded later or [`Validate`] fails,
+    /// error is logged and previous value is kept.
+    ///
+    /// [`ResourceReloaded<T>`] is sent after successful reload.
+    fn add_watched_ron_resource<T>(&mut self, filename: impl Into<String>) -> &mut Self
+    where
+        T: Resource + Serialize + DeserializeOwned + Default + Validate;
+}
+
+impl WatchedRonResourceApp for App {
+    fn add_watched_ron_resource<T>(&mut self, filename: impl Into<String>) -> &mut Self
+    where
+        T: Resource + Serialize + DeserializeOwned + Default + Validate,
+    {
+        let filename = filename.into();
+
+        let value: T = file_utils::load_valid_ron_file(&filename).unwrap_or_default();
+        file_utils::save_ron_file(&value, &filename);
+
+        self.world
+            .get_resource_or_insert_with(WatchedFiles::default)
+            .watch(&filename);
+
+        self.insert_resource(value)
+            .add_event::<ResourceReloaded<T>>()
+            .add_systems(
+                PreUpdate,
+                move |mut changes: EventReader<FileChanged>,
+                      mut value: ResMut<T>,
+                      mut reloaded: EventWriter<ResourceReloaded<T>>| {
+                    if changes.iter().any(|event| event.filename == filename) {
+                        if let Some(new_value) = file_utils::load_valid_ron_file(&filename) {
+                            info!("Reloaded \"{}\"", filename);
+                            *value = new_value;
+                            reloaded.send_default();
+                        }
+                    }
+                },
+            )
+    }
+}
+
+fn modification_time(filename: &str) -> Option<SystemTime> {
+    std::fs::metadata(filename)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn check_watched_files(
+    mut files: ResMut<WatchedFiles>,
+    mut changes: EventWriter<FileChanged>,
+    time: Res<Time>,
+    mut next_check: Local<f32>,
+) {
+    let now = time.elapsed_seconds();
+    if now < *next_check {
+        return;
+    }
+    *next_check = now + CHECK_INTERVAL;
+
+    for (filename, last_modified) in files.files.iter_mut() {
+        let modified = modification_time(filename);
+        if modified != *last_modified {
+            *last_modified = modified;
+            changes.send(FileChanged {
+                filename: filename.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Resource, Default, Serialize, Deserialize)]
+    struct TestValue {
+        value: i32,
+    }
+
+    impl Validate for TestValue {
+        fn validate(&self) -> Result<(), String> {
+            if self.value < 0 {
+                return Err("value can't be negative".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn invalid_edit_keeps_previous_value() {
+        let directory = std::env::temp_dir().join(format!("hovercrab_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let filename = directory.join("value.ron").to_string_lossy().to_string();
+        std::fs::write(&filename, "(value: 1)").unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, FileWatcherPlugin))
+            .add_watched_ron_resource::<TestValue>(filename.clone());
+        assert_eq!(app.world.resource::<TestValue>().value, 1);
+
+        // sent directly, since modification time may have coarse resolution
+        let mut edit = |data: &str| {
+            std::fs::write(&filename, data).unwrap();
+            app.world.send_event(FileChanged {
+                filename: filename.clone(),
+            });
+            app.update();
+            app.world.resource::<TestValue>().value
+        };
+
+        assert_eq!(edit("(value: 2)"), 2);
+        assert_eq!(edit("(value: -1)"), 2);
+        assert_eq!(edit("(value: "), 2);
+        assert_eq!(edit("(value: 3)"), 3);
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
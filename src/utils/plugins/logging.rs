@@ -0,0 +1,325 @@
+//! Logging to console, file and in-game UI. Replaces [`bevy::log::LogPlugin`].
+
+use crate::utils::for_crate::std::ExtendedStdResult;
+use bevy::{
+    log::Level,
+    prelude::*,
+    utils::tracing::{
+        field::{Field, Visit},
+        subscriber, Event, Metadata, Subscriber,
+    },
+};
+use lazy_static::lazy_static;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Write},
+    sync::Mutex,
+};
+use tracing_subscriber::{
+    filter::FilterFn,
+    fmt::{format::Writer, time::FormatTime},
+    layer::{Context, SubscriberExt},
+    reload, EnvFilter, Layer, Registry,
+};
+
+/// Log files are in this directory
+pub const LOG_DIRECTORY: &str = "logs";
+
+/// Sets up global logger. Must be added only once per process.
+///
+/// Filter can be overriden with `RUST_LOG` environment variable, and changed at
+/// runtime with [`LogFilter`].
+///
+/// With `trace` feature, spans are recorded for
+/// [`tracing_error::SpanTrace`]; with `trace_tracy`, they are also sent to
+/// Tracy profiler.
+pub struct LoggingPlugin {
+    /// Default filter, in [`EnvFilter`] format
+    pub filter: String,
+    /// Write log to a new file in [`LOG_DIRECTORY`]
+    pub write_file: bool,
+    /// When log file gets bigger than that (in bytes), writing continues in a
+    /// new one
+    pub max_file_size: u64,
+    /// Oldest log files are deleted so there are at most that many
+    pub max_files: usize,
+}
+
+impl Default for LoggingPlugin {
+    fn default() -> Self {
+        Self {
+            filter: "info,wgpu=error,naga=warn".to_string(),
+            write_file: true,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 10,
+        }
+    }
+}
+
+/// Changes log filter at runtime
+#[derive(Resource, Clone)]
+pub struct LogFilter {
+    current: String,
+    handle: reload::Handle<EnvFilter, Registry>,
+}
+
+impl LogFilter {
+    /// Current filter, in [`EnvFilter`] format
+    pub fn current(&self) -> &str {
+        &self.current
+    }
+
+    /// Returns error if filter is invalid; in that case current one is kept
+    pub fn set(&mut self, filter: &str) -> Result<(), String> {
+        let new_filter = EnvFilter::try_new(filter).map_err_to_string()?;
+        self.handle.reload(new_filter).map_err_to_string()?;
+        self.current = filter.to_string();
+        Ok(())
+    }
+}
+
+/// Single log record
+#[derive(Clone)]
+pub struct LogMessage {
+    pub level: Level,
+    /// Local time, already formatted
+    pub time: String,
+    /// Module path
+    pub target: String,
+    pub text: String,
+}
+
+/// Last log messages, for showing in UI
+#[derive(Resource, Default)]
+pub struct LogMessages {
+    pub messages: VecDeque<LogMessage>,
+    receiver: Option<crossbeam_channel::Receiver<LogMessage>>,
+}
+
+/// How many messages are kept in [`LogMessages`]
+const MAX_MESSAGES: usize = 1000;
+
+/// How many lines are returned by [`recent_log_lines`]
+const RECENT_LINES: usize = 200;
+
+lazy_static! {
+    static ref RECENT_LOG: Mutex<VecDeque<String>> = default();
+}
+
+/// Last log messages, formatted. Works without [`World`], i.e. in panic hook.
+///
+/// Returns nothing if log is being written right now by this thread.
+pub fn recent_log_lines() -> Vec<String> {
+    match RECENT_LOG.try_lock() {
+        Ok(lines) => lines.iter().cloned().collect(),
+        Err(std::sync::TryLockError::Poisoned(lines)) => {
+            lines.into_inner().iter().cloned().collect()
+        }
+        Err(std::sync::TryLockError::WouldBlock) => vec![],
+    }
+}
+
+impl Plugin for LoggingPlugin {
+    fn build(&self, app: &mut App) {
+        let initial_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| self.filter.clone());
+        let filter = EnvFilter::try_new(&initial_filter).unwrap_or_else(|error| {
+            eprintln!("Invalid log filter \"{initial_filter}\": {error}");
+            EnvFilter::new(&self.filter)
+        });
+        let (filter_layer, filter_handle) = reload::Layer::new(filter);
+
+        let (sender, receiver) = crossbeam_channel::bounded(MAX_MESSAGES);
+
+        // logger isn't set yet, so errors are printed directly
+        let file = match self.write_file.then(|| create_log_file(self.max_files)) {
+            Some(Ok(file)) => Some(file),
+            Some(Err(error)) => {
+                eprintln!("{error}");
+                None
+            }
+            None => None,
+        };
+        let file_layer = file.map(|file| {
+            let file = RotatingLogFile {
+                file,
+                size: 0,
+                max_size: self.max_file_size,
+                max_files: self.max_files,
+            };
+            tracing_subscriber::fmt::Layer::default()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .with_timer(LocalTime)
+                .with_filter(FilterFn::new(not_frame_mark))
+        });
+
+        let subscriber = Registry::default()
+            .with(filter_layer)
+            .with(
+                tracing_subscriber::fmt::Layer::default()
+                    .with_writer(std::io::stderr)
+                    .with_timer(LocalTime)
+                    .with_filter(FilterFn::new(not_frame_mark)),
+            )
+            .with(file_layer)
+            .with(ChannelLayer { sender }.with_filter(FilterFn::new(not_frame_mark)));
+
+        #[cfg(feature = "trace")]
+        let subscriber = subscriber.with(tracing_error::ErrorLayer::default());
+
+        #[cfg(feature = "trace_tracy")]
+        let subscriber = subscriber.with(tracing_tracy::TracyLayer::new());
+
+        if tracing_log::LogTracer::init().is_err() {
+            warn!("Global logger is already set");
+        }
+        if subscriber::set_global_default(subscriber).is_err() {
+            warn!("Global tracing subscriber is already set");
+        }
+
+        app.insert_resource(LogFilter {
+            current: initial_filter,
+            handle: filter_handle,
+        })
+        .insert_resource(LogMessages {
+            messages: default(),
+            receiver: Some(receiver),
+        })
+        .add_systems(First, receive_log_messages);
+    }
+}
+
+/// Create new file in [`LOG_DIRECTORY`] and delete old ones
+fn create_log_file(max_files: usize) -> Result<File, String> {
+    std::fs::create_dir_all(LOG_DIRECTORY)
+        .map_err(|error| format!("Failed to create \"{LOG_DIRECTORY}\": {error}"))?;
+
+    // names are sortable by time
+    let mut old_files: Vec<_> = std::fs::read_dir(LOG_DIRECTORY)
+        .map_err_to_string()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    old_files.sort();
+
+    let remove_count = (old_files.len() + 1).saturating_sub(max_files.max(1));
+    for path in old_files.iter().take(remove_count) {
+        std::fs::remove_file(path)
+            .map_err(|error| format!("Failed to remove \"{}\": {error}", path.display()))?;
+    }
+
+    // with milliseconds, so files created during one session have different names
+    let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S-%3f");
+    let filename = format!("{LOG_DIRECTORY}/{timestamp}.log");
+    File::create(&filename).map_err(|error| format!("Failed to create \"{filename}\": {error}"))
+}
+
+/// Log file which is replaced by a new one when it gets too big
+struct RotatingLogFile {
+    file: File,
+    /// Bytes written to current file
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl Write for RotatingLogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size >= self.max_size {
+            // logger can't be used from here; on error old file is kept
+            match create_log_file(self.max_files) {
+                Ok(file) => self.file = file,
+                Err(error) => eprintln!("{error}"),
+            }
+            self.size = 0;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn receive_log_messages(mut messages: ResMut<LogMessages>) {
+    let Some(receiver) = messages.receiver.clone() else {
+        return
+    };
+    for message in receiver.try_iter() {
+        if messages.messages.len() >= MAX_MESSAGES {
+            messages.messages.pop_front();
+        }
+        messages.messages.push_back(message);
+    }
+}
+
+/// `bevy_render` logs `tracy.frame_mark` event each frame, it's only for Tracy
+fn not_frame_mark(metadata: &Metadata) -> bool {
+    metadata.fields().field("tracy.frame_mark").is_none()
+}
+
+struct LocalTime;
+
+impl FormatTime for LocalTime {
+    fn format_time(&self, w: &mut Writer<'_>) -> std::fmt::Result {
+        write!(w, "{}", chrono::Local::now().format("%H:%M:%S%.3f"))
+    }
+}
+
+/// Sends events to [`LogMessages`] and keeps them for [`recent_log_lines`]
+struct ChannelLayer {
+    sender: crossbeam_channel::Sender<LogMessage>,
+}
+
+impl<S: Subscriber> Layer<S> for ChannelLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let message = LogMessage {
+            level: *metadata.level(),
+            time: chrono::Local::now().format("%H:%M:%S").to_string(),
+            target: metadata.target().to_string(),
+            text: visitor.text,
+        };
+
+        if let Ok(mut lines) = RECENT_LOG.lock() {
+            if lines.len() >= RECENT_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(format!(
+                "{} {:>5} {}: {}",
+                message.time, message.level, message.target, message.text
+            ));
+        }
+
+        // if channel is full, message is dropped
+        let _ = self.sender.try_send(message);
+    }
+}
+
+/// Formats message and other fields of an event into a single string
+#[derive(Default)]
+struct MessageVisitor {
+    text: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        use std::fmt::Write;
+
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+        let _ = match field.name() {
+            "message" => write!(self.text, "{value:?}"),
+            name => write!(self.text, "{name}={value:?}"),
+        };
+    }
+}
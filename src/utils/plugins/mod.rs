@@ -2,12 +2,21 @@
 
 use bevy::prelude::*;
 
+pub mod console;
+pub mod crash_report;
+pub mod file_watcher;
+pub mod logging;
 pub mod scene_utils;
+pub mod settings;
 
 pub struct UtilPlugins;
 
 impl Plugin for UtilPlugins {
     fn build(&self, app: &mut App) {
-        app.add_plugins((scene_utils::SceneUtilsPlugin,));
+        app.add_plugins((
+            console::ConsolePlugin,
+            file_watcher::FileWatcherPlugin,
+            scene_utils::SceneUtilsPlugin,
+        ));
     }
 }
@@ -32,7 +32,10 @@ fn spawn_static_colliders(
 ) {
     use bevy_rapier3d::prelude::*;
 
+    let _span = info_span!("spawn_static_colliders").entered();
+
     for (root, root_transform) in entities.iter() {
+        let _span = debug_span!("scene", entity = ?root).entered();
         let mut colliders = vec![];
 
         // collect all colliders and their transforms relative to root
@@ -0,0 +1,82 @@
+//! Settings stored in RON files
+
+use crate::utils::{
+    file_utils::{self, Validate},
+    plugins::{
+        crash_report::set_crash_settings,
+        file_watcher::{ResourceReloaded, WatchedFiles, WatchedRonResourceApp},
+    },
+};
+use bevy::{app::AppExit, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+
+/// All settings files are in this directory
+pub const SETTINGS_DIRECTORY: &str = "config";
+
+/// Settings are saved when they don't change for that long, or on exit
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Systems which save changed settings. Settings changed after this set will be
+/// saved only on the next frame.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct SaveSettingsSet;
+
+/// Adds methods to [`App`] for settings resources.
+pub trait SettingsApp {
+    /// Load settings resource at startup and save it when it stops changing,
+    /// or on exit.
+    /// Current value is also kept for crash report, see [`set_crash_settings`].
+    ///
+    /// File is named `<name>.ron` and is in [`SETTINGS_DIRECTORY`]. It's also
+    /// reloaded when changed on disk, see
+    /// [`WatchedRonResourceApp::add_watched_ron_resource`].
+    fn add_settings<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: Resource + Serialize + DeserializeOwned + Default + Validate;
+}
+
+impl SettingsApp for App {
+    fn add_settings<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: Resource + Serialize + DeserializeOwned + Default + Validate,
+    {
+        let filename = settings_filename(name);
+
+        self.add_watched_ron_resource::<T>(filename.clone())
+            .add_systems(
+                Last,
+                (move |value: Res<T>,
+                       mut reloaded: EventReader<ResourceReloaded<T>>,
+                       mut exit: EventReader<AppExit>,
+                       mut watched_files: ResMut<WatchedFiles>,
+                       time: Res<Time>,
+                       mut save_at: Local<Option<Duration>>| {
+                    if value.is_changed() {
+                        set_crash_settings(&filename, &*value);
+                    }
+
+                    // don't write back what was just read
+                    if reloaded.iter().count() != 0 {
+                        *save_at = None;
+                    } else if value.is_changed() && !value.is_added() {
+                        // value may change every frame, i.e. while zooming
+                        *save_at = Some(time.raw_elapsed() + SAVE_DELAY);
+                    }
+
+                    let exiting = exit.iter().count() != 0;
+                    if save_at.is_some_and(|at| exiting || time.raw_elapsed() >= at) {
+                        file_utils::save_ron_file(&*value, &filename);
+                        watched_files.ignore_changes(&filename);
+                        *save_at = None;
+                    }
+                })
+                .in_set(SaveSettingsSet),
+            )
+    }
+}
+
+/// Path to the settings file with that name
+pub fn settings_filename(name: &str) -> String {
+    format!("{SETTINGS_DIRECTORY}/{name}.ron")
+}